@@ -0,0 +1,56 @@
+//! Module that defines the list of catalogs of the session.
+//! DataFusion's default list can not forget a catalog once it has been registered, this one
+//! allows catalogs to be dropped
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use datafusion::catalog::{CatalogProvider, CatalogProviderList};
+
+/// A [`CatalogProviderList`] whose catalogs can be deregistered
+#[derive(Default)]
+pub(crate) struct CatalogList {
+    catalogs: RwLock<HashMap<String, Arc<dyn CatalogProvider>>>,
+}
+
+impl CatalogList {
+    /// Remove a catalog from the list, returning it if it was registered
+    pub(crate) fn deregister_catalog(&self, name: &str) -> Option<Arc<dyn CatalogProvider>> {
+        self.catalogs.write().expect("lock poisoned").remove(name)
+    }
+}
+
+impl CatalogProviderList for CatalogList {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn register_catalog(
+        &self,
+        name: String,
+        catalog: Arc<dyn CatalogProvider>,
+    ) -> Option<Arc<dyn CatalogProvider>> {
+        self.catalogs
+            .write()
+            .expect("lock poisoned")
+            .insert(name, catalog)
+    }
+
+    fn catalog_names(&self) -> Vec<String> {
+        self.catalogs
+            .read()
+            .expect("lock poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn catalog(&self, name: &str) -> Option<Arc<dyn CatalogProvider>> {
+        self.catalogs
+            .read()
+            .expect("lock poisoned")
+            .get(name)
+            .cloned()
+    }
+}
@@ -1,7 +1,7 @@
 //! Module that defines tauri commands to create resources
 
 use crate::{
-    common::DatasourceConfig,
+    common::{CreateTableOptions, DatasourceConfig},
     lens::{Lens, LensResult},
 };
 
@@ -10,3 +10,30 @@ pub fn create_datasource(lens: tauri::State<'_, Lens>, config: DatasourceConfig)
     lens.register_datasource(config)?;
     Ok(())
 }
+
+#[tauri::command]
+pub async fn create_table(
+    lens: tauri::State<'_, Lens>,
+    options: CreateTableOptions,
+) -> LensResult<()> {
+    lens.create_table(options).await
+}
+
+#[tauri::command]
+pub fn create_schema(
+    lens: tauri::State<'_, Lens>,
+    catalog: Option<String>,
+    name: String,
+    if_not_exists: bool,
+) -> LensResult<()> {
+    lens.create_schema(catalog, &name, if_not_exists)
+}
+
+#[tauri::command]
+pub fn create_catalog(
+    lens: tauri::State<'_, Lens>,
+    name: String,
+    if_not_exists: bool,
+) -> LensResult<()> {
+    lens.create_catalog(&name, if_not_exists)
+}
@@ -0,0 +1,23 @@
+//! Module that defines tauri commands to drop resources
+
+use crate::lens::{Lens, LensResult};
+
+#[tauri::command]
+pub fn drop_table(lens: tauri::State<'_, Lens>, table: String, if_exists: bool) -> LensResult<()> {
+    lens.drop_table(&table, if_exists)
+}
+
+#[tauri::command]
+pub fn drop_schema(
+    lens: tauri::State<'_, Lens>,
+    catalog: Option<String>,
+    name: String,
+    cascade: bool,
+) -> LensResult<()> {
+    lens.drop_schema(catalog, &name, cascade)
+}
+
+#[tauri::command]
+pub fn drop_catalog(lens: tauri::State<'_, Lens>, name: String, cascade: bool) -> LensResult<()> {
+    lens.drop_catalog(&name, cascade)
+}
@@ -0,0 +1,34 @@
+//! Module that defines tauri commands to inspect files stored in the registered object stores
+
+use crate::{
+    common::{FilePreview, ObjectInfo, ParquetMetadata, TableFormat},
+    lens::{Lens, LensResult},
+    parquet_metadata,
+};
+
+#[tauri::command]
+pub async fn parquet_metadata(
+    lens: tauri::State<'_, Lens>,
+    path: String,
+) -> LensResult<ParquetMetadata> {
+    Ok(parquet_metadata::read(&lens.context().state(), &path).await?)
+}
+
+#[tauri::command]
+pub async fn head_object(
+    lens: tauri::State<'_, Lens>,
+    datasource: String,
+    path: String,
+) -> LensResult<ObjectInfo> {
+    lens.head_object(&datasource, &path).await
+}
+
+#[tauri::command]
+pub async fn preview_file(
+    lens: tauri::State<'_, Lens>,
+    path: String,
+    format: Option<TableFormat>,
+    limit: Option<usize>,
+) -> LensResult<FilePreview> {
+    lens.preview_file(&path, format, limit).await
+}
@@ -1,8 +1,9 @@
 //! Module that defines commands to list previously created or already existing resources
 
 use crate::{
-    common::{self, DatasourceConfig},
+    common::{self, DatasourceConfig, ObjectPage},
     lens::{Lens, LensResult},
+    table_provider,
 };
 
 #[tauri::command]
@@ -35,10 +36,12 @@ pub async fn list_databases(lens: tauri::State<'_, Lens>) -> LensResult<Vec<comm
                 };
 
                 let schema = table.schema();
+                let partitions = table_provider::partition_columns(table.as_ref());
 
                 tables.push(common::Table {
                     name: table_name.clone(),
                     schema,
+                    partitions,
                 });
             }
 
@@ -56,3 +59,22 @@ pub async fn list_databases(lens: tauri::State<'_, Lens>) -> LensResult<Vec<comm
 
     Ok(catalogs)
 }
+
+#[tauri::command]
+pub async fn list_objects(
+    lens: tauri::State<'_, Lens>,
+    datasource: String,
+    prefix: Option<String>,
+    delimiter: bool,
+    page_token: Option<String>,
+    page_size: Option<usize>,
+) -> LensResult<ObjectPage> {
+    lens.list_objects(
+        &datasource,
+        prefix.as_deref().unwrap_or_default(),
+        delimiter,
+        page_token,
+        page_size,
+    )
+    .await
+}
@@ -1,5 +1,8 @@
 pub mod aws;
 pub mod create;
+pub mod drop;
+pub mod file;
 pub mod list;
 pub mod sql;
 pub mod stream;
+pub mod table;
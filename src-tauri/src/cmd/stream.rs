@@ -1,7 +1,7 @@
 //! Module that defines commands related to stream manipulation
 
 use crate::{
-    common::{ExportOptions, StreamId, StreamInfo},
+    common::{CopyOptions, ExportOptions, StreamId, StreamInfo},
     lens::{Lens, LensResult},
 };
 
@@ -14,6 +14,15 @@ pub async fn stream_export(
     lens.stream_export(id, options).await
 }
 
+#[tauri::command]
+pub async fn stream_copy(
+    lens: tauri::State<'_, Lens>,
+    id: StreamId,
+    options: CopyOptions,
+) -> LensResult<String> {
+    lens.stream_copy(id, options).await
+}
+
 #[tauri::command]
 pub async fn stream_close(lens: tauri::State<'_, Lens>, id: StreamId) -> LensResult<()> {
     lens.stream_close(id).await
@@ -0,0 +1,58 @@
+//! Module that defines commands to inspect and manage tables registered in DataFusion' context
+
+use anyhow::anyhow;
+use datafusion::{datasource::listing::ListingTable, sql::TableReference};
+
+use crate::{
+    common::{FileSchemaDiff, TableStatistics},
+    lens::{Lens, LensResult},
+    table_provider,
+};
+
+#[tauri::command]
+pub async fn table_schema_diff(
+    lens: tauri::State<'_, Lens>,
+    table: String,
+) -> LensResult<Vec<FileSchemaDiff>> {
+    let context = lens.context();
+    let provider = context
+        .table_provider(TableReference::parse_str(&table))
+        .await?;
+
+    let Some(listing) = provider.as_any().downcast_ref::<ListingTable>() else {
+        return Err(anyhow!("table {table} is not backed by files").into());
+    };
+
+    Ok(table_provider::schema_diff(&context.state(), listing).await?)
+}
+
+#[tauri::command]
+pub async fn table_statistics(
+    lens: tauri::State<'_, Lens>,
+    table: String,
+) -> LensResult<TableStatistics> {
+    let context = lens.context();
+    let provider = context
+        .table_provider(TableReference::parse_str(&table))
+        .await?;
+
+    let Some(listing) = provider.as_any().downcast_ref::<ListingTable>() else {
+        return Err(anyhow!("table {table} is not backed by files").into());
+    };
+
+    Ok(table_provider::table_statistics(&context.state(), listing).await?)
+}
+
+#[tauri::command]
+pub fn rename_table(
+    lens: tauri::State<'_, Lens>,
+    table: String,
+    new_name: String,
+) -> LensResult<()> {
+    lens.rename_table(&table, &new_name)
+}
+
+#[tauri::command]
+pub async fn refresh_table(lens: tauri::State<'_, Lens>, table: String) -> LensResult<()> {
+    lens.refresh_table(&table).await
+}
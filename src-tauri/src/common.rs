@@ -1,8 +1,17 @@
 //! Module that defines common types that are exchanged between the frontend and the backend
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use datafusion::dataframe::DataFrameWriteOptions;
+use datafusion::{
+    arrow::{
+        array::RecordBatch,
+        error::ArrowError,
+        util::display::{ArrayFormatter, FormatOptions},
+    },
+    config::TableParquetOptions,
+    datasource::file_format::file_compression_type::FileCompressionType,
+};
+use object_store::ObjectMeta;
 use serde::{Deserialize, Serialize};
 
 /// A database (or catalog) registered in DataFusion' context
@@ -33,6 +42,10 @@ pub struct Table {
 
     /// Associated DataFusion [`Schema`] of this table
     pub schema: Arc<datafusion::arrow::datatypes::Schema>,
+
+    /// Name of the partition columns of this table, either declared or discovered from the
+    /// layout of its files. Partition columns are also part of the schema
+    pub partitions: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
@@ -60,6 +73,48 @@ pub struct Row {
     pub values: Vec<String>,
 }
 
+impl Row {
+    /// Format the values of the rows of a record batch
+    pub fn from_batch(batch: &RecordBatch) -> Result<Vec<Self>, ArrowError> {
+        let schema = batch.schema();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|f| f.name())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let options = FormatOptions::default().with_display_error(true);
+        let formatters = batch
+            .columns()
+            .iter()
+            .map(|c| ArrayFormatter::try_new(c.as_ref(), &options))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let rows = (0..batch.num_rows())
+            .map(|row| Self {
+                columns: columns.clone(),
+                values: formatters
+                    .iter()
+                    .map(|f| f.value(row).to_string())
+                    .collect(),
+            })
+            .collect();
+
+        Ok(rows)
+    }
+}
+
+/// Schema and first rows of a file, read without registering a table
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    /// Format used to read the file, inferred from its extension when not given
+    pub format: TableFormat,
+    pub schema: Arc<datafusion::arrow::datatypes::Schema>,
+    pub rows: Vec<Row>,
+}
+
 /// Configuration for an object storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "kind", content = "config")]
@@ -82,12 +137,197 @@ pub enum ObjectStoreConfig {
     },
 }
 
+/// A column whose type in a file differs from its type in the table
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnTypeChange {
+    pub name: String,
+    pub file_type: String,
+    pub table_type: String,
+}
+
+/// Differences between the schema of a file and the schema of its table
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSchemaDiff {
+    /// Location of the file in its object store
+    pub path: String,
+
+    /// Columns of the table that are missing from the file, read as nulls
+    pub missing_columns: Vec<String>,
+
+    /// Columns of the file that are not part of the table, which are not read
+    pub extra_columns: Vec<String>,
+
+    /// Columns whose values are casted to the type of the table when read
+    pub changed_columns: Vec<ColumnTypeChange>,
+}
+
+/// Statistics of a column of a table, aggregated over its files.
+/// Values are unknown when they are not available for every file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnStatistics {
+    pub name: String,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub null_count: Option<usize>,
+}
+
+/// Statistics of the files of a partition of a table
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionStatistics {
+    /// Values of the partition columns, in the order of the columns
+    pub values: Vec<String>,
+    pub file_count: usize,
+    pub total_size: usize,
+    pub row_count: Option<usize>,
+}
+
+/// Statistics of a table backed by files, read from the metadata of its files
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStatistics {
+    pub file_count: usize,
+
+    /// Size of the files, in bytes
+    pub total_size: usize,
+    pub row_count: Option<usize>,
+
+    /// Statistics of the columns read from the files, partition columns excluded
+    pub columns: Vec<ColumnStatistics>,
+    pub partitions: Vec<PartitionStatistics>,
+}
+
+/// Statistics of a Parquet column chunk, as stored in the file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetChunkStatistics {
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub null_count: u64,
+    pub distinct_count: Option<u64>,
+}
+
+/// A chunk of a column within a Parquet row group
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetColumnChunk {
+    /// Dotted path of the column in the Parquet schema
+    pub path: String,
+    pub physical_type: String,
+    pub compression: String,
+    pub encodings: Vec<String>,
+    pub num_values: i64,
+    pub compressed_size: i64,
+    pub uncompressed_size: i64,
+    pub data_page_offset: i64,
+    pub dictionary_page_offset: Option<i64>,
+    pub statistics: Option<ParquetChunkStatistics>,
+    pub has_column_index: bool,
+    pub has_offset_index: bool,
+    pub has_bloom_filter: bool,
+}
+
+/// A row group of a Parquet file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetRowGroup {
+    pub ordinal: Option<i16>,
+    pub num_rows: i64,
+    pub total_byte_size: i64,
+    pub compressed_size: i64,
+    pub columns: Vec<ParquetColumnChunk>,
+}
+
+/// A leaf column of a Parquet schema
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetColumn {
+    pub path: String,
+    pub physical_type: String,
+    pub logical_type: Option<String>,
+    pub converted_type: String,
+    pub max_definition_level: i16,
+    pub max_repetition_level: i16,
+}
+
+/// Footer of a Parquet file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParquetMetadata {
+    pub path: String,
+    pub size: usize,
+    pub version: i32,
+    pub num_rows: i64,
+    pub created_by: Option<String>,
+
+    /// The Parquet schema, in its message type notation
+    pub schema: String,
+    pub columns: Vec<ParquetColumn>,
+    pub row_groups: Vec<ParquetRowGroup>,
+    pub key_value_metadata: HashMap<String, Option<String>>,
+}
+
+/// An object of an object store
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectInfo {
+    /// Location of the object, relative to the url of its data source
+    pub path: String,
+
+    /// Size of the object, in bytes
+    pub size: usize,
+
+    /// Last modification of the object, in RFC 3339 format
+    pub last_modified: String,
+    pub e_tag: Option<String>,
+}
+
+impl From<ObjectMeta> for ObjectInfo {
+    fn from(meta: ObjectMeta) -> Self {
+        Self {
+            path: meta.location.to_string(),
+            size: meta.size,
+            last_modified: meta.last_modified.to_rfc3339(),
+            e_tag: meta.e_tag,
+        }
+    }
+}
+
+/// A page of the objects and common prefixes found under a prefix of a data source
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectPage {
+    /// Url of the data source, the url of an entry is `{base_url}/{path}`
+    pub base_url: String,
+
+    /// Prefixes of the objects that are one level deeper, when listed with a delimiter
+    pub prefixes: Vec<String>,
+    pub objects: Vec<ObjectInfo>,
+
+    /// Token to pass to get the next page, if any
+    pub next_page_token: Option<String>,
+}
+
 /// Configuration for a data source
 /// A data source is a location where data can be found and queried against
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatasourceConfig {
     pub url: String,
     pub store: ObjectStoreConfig,
+
+    /// Name used to reference the data source, its url is used if not set
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl DatasourceConfig {
+    /// Whether this data source is referenced by `name`
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.as_deref().unwrap_or(&self.url) == name
+    }
 }
 
 /// A configuration for an AWS IAM SSO profile retrieved from the configuration file
@@ -110,21 +350,420 @@ pub struct AwsSsoProfile {
     pub role_name: String,
 }
 
+/// Template of the names of exported files.
+/// Files are named `<prefix>[_<timestamp>][_<sequence>].<extension>`, the sequence number being
+/// omitted when a single file is written in a directory, e.g. a hive-style partition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FileNameTemplate {
+    pub prefix: String,
+
+    /// Add the UTC timestamp of the export, formatted as `YYYYMMDDTHHMMSSZ`
+    pub timestamp: bool,
+
+    /// Minimum number of digits of the sequence number, padded with zeros
+    pub sequence_digits: usize,
+}
+
+impl Default for FileNameTemplate {
+    fn default() -> Self {
+        Self {
+            prefix: "part".to_string(),
+            timestamp: false,
+            sequence_digits: 5,
+        }
+    }
+}
+
+/// Behavior of an export when its target location already holds data
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WriteMode {
+    /// Fail if the target file exists or if the target directory is not empty
+    #[default]
+    ErrorIfExists,
+
+    /// Write new files next to the existing ones.
+    /// Single files can not be appended to, rows are appended to the table of database exports
+    Append,
+
+    /// Replace the target file or everything under the target directory, nested directories
+    /// included. Existing files are only deleted once the export succeeded.
+    /// The table of database exports is replaced
+    Overwrite,
+}
+
+/// SQL expression that partitions exported data, such as `date_trunc('day', ts)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionExpr {
+    /// Name of the partition, used to name directories as `name=value`
+    pub name: String,
+    pub expr: String,
+}
+
 /// Write options to export data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WriteOptions {
-    pub overwrite: bool,
+    #[serde(default)]
+    pub mode: WriteMode,
+
+    /// Write a single file in the target directory, or in each partition. Exports to the path
+    /// of a file always write a single file
     pub single_file: bool,
+
+    /// Columns to partition by, their values are written in directory names as strings
     pub partition_by: Vec<String>,
+
+    /// Expressions to partition by, after the `partition_by` columns
+    #[serde(default)]
+    pub partition_exprs: Vec<PartitionExpr>,
+
+    /// Number of rows after which a new file is started.
+    /// This is a soft limit as a record batch is never split across files
+    pub max_rows_per_file: Option<usize>,
+
+    /// Maximum number of record batches buffered for each file being written
+    pub max_buffered_batches: Option<usize>,
+
+    /// Minimum number of files written in parallel when data is not partitioned
+    pub minimum_parallel_files: Option<usize>,
+
+    /// Template of the names of exported files, files are named with a random identifier if not
+    /// set
+    pub file_name: Option<FileNameTemplate>,
+}
+
+/// Character sequence used to terminate lines when exporting data
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineTerminator {
+    /// Unix-style line feed `\n`
+    #[default]
+    Lf,
+
+    /// Windows-style carriage return followed by a line feed `\r\n`
+    Crlf,
 }
 
+/// Options to export data in CSV
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CsvExportOptions {
+    /// Character used to separate fields
+    pub delimiter: char,
+
+    /// Character used to quote fields
+    pub quote: char,
+
+    /// Character used to escape quotes inside quoted fields.
+    /// When not set, quotes are escaped by doubling them
+    pub escape: Option<char>,
+
+    /// Whether to write the name of the columns as the first line
+    pub has_header: bool,
+
+    /// Representation of null values
+    pub null_value: String,
+
+    /// Format of `Date` values
+    pub date_format: Option<String>,
+
+    /// Format of `Timestamp` values without timezone
+    pub timestamp_format: Option<String>,
+
+    /// Format of `Timestamp` values with a timezone
+    pub timestamp_tz_format: Option<String>,
+
+    /// Format of `Time` values
+    pub time_format: Option<String>,
+
+    /// Line terminator
+    pub terminator: LineTerminator,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            escape: None,
+            has_header: true,
+            null_value: String::new(),
+            date_format: None,
+            timestamp_format: None,
+            timestamp_tz_format: None,
+            time_format: None,
+            terminator: LineTerminator::default(),
+        }
+    }
+}
+
+/// Compression codec used to write Parquet files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ParquetCompression {
+    Uncompressed,
+    Snappy,
+    Gzip,
+    Lzo,
+    Brotli,
+    Lz4,
+    Lz4Raw,
+    Zstd,
+}
+
+/// Statistics level written in Parquet files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub enum ParquetStatistics {
+    None,
+    Chunk,
+    Page,
+}
+
+/// Version of the Parquet writer
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ParquetWriterVersion {
+    #[serde(rename = "1.0")]
+    V1,
+
+    #[serde(rename = "2.0")]
+    V2,
+}
+
+/// Bloom filter configuration of a Parquet column
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ParquetBloomFilter {
+    /// False positive probability of the filter
+    pub fpp: Option<f64>,
+
+    /// Number of distinct values expected in the column
+    pub ndv: Option<u64>,
+}
+
+/// Options to export data in Parquet
+/// Options that are not set fall back to the defaults of the session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ParquetExportOptions {
+    /// Compression codec
+    pub compression: Option<ParquetCompression>,
+
+    /// Compression level, only used by the codecs that support it (gzip, brotli, zstd)
+    pub compression_level: Option<u32>,
+
+    /// Maximum number of rows in a row group
+    pub max_row_group_size: Option<usize>,
+
+    /// Whether to enable dictionary encoding
+    pub dictionary_enabled: Option<bool>,
+
+    /// Level of statistics to write
+    pub statistics_enabled: Option<ParquetStatistics>,
+
+    /// Bloom filters to write, by column name
+    pub bloom_filters: HashMap<String, ParquetBloomFilter>,
+
+    /// Version of the writer
+    pub writer_version: Option<ParquetWriterVersion>,
+
+    /// Key-value metadata to write in the file footer
+    pub key_value_metadata: HashMap<String, String>,
+}
+
+/// Options to export data in JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct JsonExportOptions {
+    /// Whether to write null values explicitly instead of omitting their key
+    pub explicit_nulls: bool,
+
+    /// Format of `Date` values
+    pub date_format: Option<String>,
+
+    /// Format of `Timestamp` values without timezone
+    pub timestamp_format: Option<String>,
+
+    /// Format of `Timestamp` values with a timezone
+    pub timestamp_tz_format: Option<String>,
+
+    /// Format of `Time` values
+    pub time_format: Option<String>,
+}
+
+/// Layout of exported Arrow IPC data
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArrowIpcLayout {
+    /// IPC file format, also known as Feather V2, that supports random access
+    #[default]
+    File,
+
+    /// IPC streaming format
+    Stream,
+}
+
+/// Compression codec used to compress Arrow IPC buffers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArrowIpcCompression {
+    Lz4,
+    Zstd,
+}
+
+/// Options to export data in Arrow IPC
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ArrowIpcExportOptions {
+    pub layout: ArrowIpcLayout,
+
+    /// Compression of the IPC buffers, if any
+    pub compression: Option<ArrowIpcCompression>,
+}
+
+/// Options to export data in an Excel workbook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct XlsxExportOptions {
+    /// Name of the worksheet when data is not partitioned.
+    /// Partitioned data is written to one worksheet per partition, named after the partition key
+    pub sheet_name: Option<String>,
+
+    /// Excel number format of `Date` cells
+    pub date_format: String,
+
+    /// Excel number format of `Timestamp` cells
+    pub datetime_format: String,
+}
+
+impl Default for XlsxExportOptions {
+    fn default() -> Self {
+        Self {
+            sheet_name: None,
+            date_format: "yyyy-mm-dd".to_string(),
+            datetime_format: "yyyy-mm-dd hh:mm:ss".to_string(),
+        }
+    }
+}
+
+/// Codec used to compress the blocks of an Avro object container file
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AvroCodec {
+    #[default]
+    Null,
+    Deflate,
+    Snappy,
+    Zstandard,
+    Bzip2,
+    Xz,
+}
+
+/// Options to export data in Avro object container files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AvroExportOptions {
+    pub codec: AvroCodec,
+
+    /// Name of the Avro record derived from the schema of the exported data
+    pub record_name: String,
+}
+
+impl Default for AvroExportOptions {
+    fn default() -> Self {
+        Self {
+            codec: AvroCodec::default(),
+            record_name: "record".to_string(),
+        }
+    }
+}
+
+/// SQL dialect of exported scripts
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SqlDialect {
+    #[default]
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+/// Options to export data as a script of SQL `INSERT` statements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SqlExportOptions {
+    pub dialect: SqlDialect,
+
+    /// Name of the table to insert rows into
+    pub table_name: String,
+
+    /// Maximum number of rows inserted by a single `INSERT` statement
+    pub rows_per_statement: usize,
+
+    /// Start every script with a `CREATE TABLE` statement derived from the schema of the data
+    pub create_table: bool,
+}
+
+impl Default for SqlExportOptions {
+    fn default() -> Self {
+        Self {
+            dialect: SqlDialect::default(),
+            table_name: "export".to_string(),
+            rows_per_statement: 100,
+            create_table: false,
+        }
+    }
+}
+
+/// Options to export data into a table of a database file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DatabaseExportOptions {
+    /// Name of the table to create or append to
+    pub table_name: String,
+}
+
+impl Default for DatabaseExportOptions {
+    fn default() -> Self {
+        Self {
+            table_name: "export".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "options", rename_all = "camelCase")]
 pub enum ExportFormat {
-    Csv,
-    Parquet,
-    Json,
+    Csv(CsvExportOptions),
+    Parquet(ParquetExportOptions),
+    /// JSON array of objects
+    Json(JsonExportOptions),
+    /// Newline-delimited JSON, one object per line
+    Ndjson(JsonExportOptions),
+    ArrowIpc(ArrowIpcExportOptions),
+    Xlsx(XlsxExportOptions),
+    Avro(AvroExportOptions),
+    /// Script of SQL `INSERT` statements
+    Sql(SqlExportOptions),
+    /// Table of a SQLite database file
+    Sqlite(DatabaseExportOptions),
+    /// Table of a DuckDB database file
+    Duckdb(DatabaseExportOptions),
+}
+
+/// Compression of files, either read by a table or written by an export
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileCompression {
+    #[default]
+    Uncompressed,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
 }
 
 /// Options to export data
@@ -133,7 +772,131 @@ pub enum ExportFormat {
 pub struct ExportOptions {
     pub format: ExportFormat,
     pub write_options: WriteOptions,
+
+    /// Location of the export.
+    /// When a datasource is set, the path is relative to the url of the datasource
     pub path: String,
+
+    /// Name of the registered datasource to export to
+    #[serde(default)]
+    pub datasource: Option<String>,
+
+    /// Compression applied to the exported files.
+    /// Parquet, Arrow IPC and Avro files are not affected as they are compressed with their own
+    /// codec. XLSX files are already compressed and are not affected either
+    #[serde(default)]
+    pub compression: FileCompression,
+}
+
+/// Options to read the CSV files of a table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CsvTableOptions {
+    /// Whether the first line of the files holds the name of the columns
+    pub has_header: bool,
+
+    /// Character used to separate fields
+    pub delimiter: char,
+
+    /// Character used to quote fields
+    pub quote: char,
+
+    /// Character used to escape quotes inside quoted fields
+    pub escape: Option<char>,
+
+    pub compression: FileCompression,
+}
+
+impl Default for CsvTableOptions {
+    fn default() -> Self {
+        Self {
+            has_header: true,
+            delimiter: ',',
+            quote: '"',
+            escape: None,
+            compression: FileCompression::Uncompressed,
+        }
+    }
+}
+
+/// Options to read the newline-delimited JSON files of a table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct JsonTableOptions {
+    pub compression: FileCompression,
+}
+
+/// File format of a table, with its options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "options", rename_all = "camelCase")]
+pub enum TableFormat {
+    Csv(CsvTableOptions),
+    Json(JsonTableOptions),
+    Parquet,
+    Avro,
+    Arrow,
+}
+
+/// Options to create an external table without writing SQL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTableOptions {
+    /// Catalog of the table, the default catalog is used if not set
+    pub catalog: Option<String>,
+
+    /// Schema of the table, the default schema is used if not set
+    pub schema: Option<String>,
+
+    /// Name of the table, unquoted names are lowercased like SQL identifiers
+    pub name: String,
+
+    /// Locations of the files of the table
+    pub locations: Vec<String>,
+
+    pub format: TableFormat,
+
+    /// Partition columns, discovered from the `key=value` directories of the files if empty.
+    /// Directories named after a column of the files are not partitions
+    #[serde(default)]
+    pub partition_by: Vec<String>,
+
+    /// Glob patterns of the files to read, relative to each location
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Glob patterns of the files to skip, relative to each location
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Do not fail if the table already exists
+    #[serde(default)]
+    pub if_not_exists: bool,
+}
+
+/// Markup language of a table copied to the clipboard
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TableMarkup {
+    /// GitHub-flavored Markdown table
+    Markdown,
+    Html,
+}
+
+/// Options to copy rows of a stream as a table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyOptions {
+    pub markup: TableMarkup,
+
+    /// Index of the first row to copy
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Maximum number of rows to copy, copies every row that has been fetched so far if not set
+    pub limit: Option<usize>,
+
+    /// Maximum number of characters of a cell, longer values are truncated with an ellipsis
+    pub max_cell_width: Option<usize>,
 }
 
 /// Information about a [`StreamId`] stream that is currently executing or has been executed
@@ -150,17 +913,188 @@ pub struct StreamInfo {
     pub rows: usize,
 }
 
-impl Into<DataFrameWriteOptions> for WriteOptions {
-    fn into(self) -> DataFrameWriteOptions {
+impl From<FileCompression> for FileCompressionType {
+    fn from(value: FileCompression) -> Self {
+        match value {
+            FileCompression::Uncompressed => FileCompressionType::UNCOMPRESSED,
+            FileCompression::Gzip => FileCompressionType::GZIP,
+            FileCompression::Zstd => FileCompressionType::ZSTD,
+            FileCompression::Bzip2 => FileCompressionType::BZIP2,
+            FileCompression::Xz => FileCompressionType::XZ,
+        }
+    }
+}
+
+impl FileCompression {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Uncompressed => "uncompressed",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+        }
+    }
+}
+
+impl TableFormat {
+    /// Guess the format of a file from its extensions, e.g. `events.csv.gz`
+    pub fn from_path(path: &str) -> Option<Self> {
+        let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+        let (name, compression) = match name.rsplit_once('.') {
+            Some((stem, "gz")) => (stem, FileCompression::Gzip),
+            Some((stem, "zst")) => (stem, FileCompression::Zstd),
+            Some((stem, "bz2")) => (stem, FileCompression::Bzip2),
+            Some((stem, "xz")) => (stem, FileCompression::Xz),
+            _ => (name.as_str(), FileCompression::Uncompressed),
+        };
+        let (_, extension) = name.rsplit_once('.')?;
+
+        // Only text formats can be compressed as a whole
+        let uncompressed = matches!(compression, FileCompression::Uncompressed);
+        Some(match extension {
+            "csv" => Self::Csv(CsvTableOptions {
+                compression,
+                ..Default::default()
+            }),
+            "tsv" => Self::Csv(CsvTableOptions {
+                delimiter: '\t',
+                compression,
+                ..Default::default()
+            }),
+            "json" | "ndjson" | "jsonl" => Self::Json(JsonTableOptions { compression }),
+            "parquet" | "parq" if uncompressed => Self::Parquet,
+            "avro" if uncompressed => Self::Avro,
+            "arrow" | "ipc" | "feather" if uncompressed => Self::Arrow,
+            _ => return None,
+        })
+    }
+
+    /// DataFusion file type of the format
+    pub fn file_type(&self) -> &'static str {
+        match self {
+            Self::Csv(_) => "CSV",
+            Self::Json(_) => "JSON",
+            Self::Parquet => "PARQUET",
+            Self::Avro => "AVRO",
+            Self::Arrow => "ARROW",
+        }
+    }
+
+    /// Table options of the format, as given in the `OPTIONS` of `CREATE EXTERNAL TABLE`
+    pub fn table_options(&self) -> HashMap<String, String> {
+        let mut options = HashMap::new();
+        let mut set = |key: &str, value: String| {
+            options.insert(format!("format.{key}"), value);
+        };
+
+        match self {
+            Self::Csv(csv) => {
+                set("has_header", csv.has_header.to_string());
+                set("delimiter", csv.delimiter.to_string());
+                set("quote", csv.quote.to_string());
+                if let Some(escape) = csv.escape {
+                    set("escape", escape.to_string());
+                }
+                set("compression", csv.compression.as_str().to_string());
+            }
+            Self::Json(json) => set("compression", json.compression.as_str().to_string()),
+            Self::Parquet | Self::Avro | Self::Arrow => {}
+        }
+
+        options
+    }
+}
+
+impl ParquetCompression {
+    fn codec(self) -> &'static str {
+        match self {
+            Self::Uncompressed => "uncompressed",
+            Self::Snappy => "snappy",
+            Self::Gzip => "gzip",
+            Self::Lzo => "lzo",
+            Self::Brotli => "brotli",
+            Self::Lz4 => "lz4",
+            Self::Lz4Raw => "lz4_raw",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+impl ParquetStatistics {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Chunk => "chunk",
+            Self::Page => "page",
+        }
+    }
+}
+
+impl ParquetWriterVersion {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::V1 => "1.0",
+            Self::V2 => "2.0",
+        }
+    }
+}
+
+impl ParquetExportOptions {
+    /// Apply these options on top of the `base` [`TableParquetOptions`]
+    pub fn into_table_options(self, base: TableParquetOptions) -> TableParquetOptions {
         let Self {
-            overwrite,
-            single_file,
-            partition_by,
+            compression,
+            compression_level,
+            max_row_group_size,
+            dictionary_enabled,
+            statistics_enabled,
+            bloom_filters,
+            writer_version,
+            key_value_metadata,
         } = self;
 
-        DataFrameWriteOptions::new()
-            .with_overwrite(overwrite)
-            .with_single_file_output(single_file)
-            .with_partition_by(partition_by)
+        let mut options = base;
+        let global = &mut options.global;
+
+        if let Some(compression) = compression {
+            // DataFusion expects the level to be specified with the codec, e.g `zstd(3)`
+            global.compression = Some(match compression_level {
+                Some(level) => format!("{}({level})", compression.codec()),
+                None => compression.codec().to_string(),
+            });
+        }
+
+        if let Some(max_row_group_size) = max_row_group_size {
+            global.max_row_group_size = max_row_group_size;
+        }
+
+        if let Some(dictionary_enabled) = dictionary_enabled {
+            global.dictionary_enabled = Some(dictionary_enabled);
+        }
+
+        if let Some(statistics_enabled) = statistics_enabled {
+            global.statistics_enabled = Some(statistics_enabled.as_str().to_string());
+        }
+
+        if let Some(writer_version) = writer_version {
+            global.writer_version = writer_version.as_str().to_string();
+        }
+
+        for (column, bloom_filter) in bloom_filters {
+            let column_options = options.column_specific_options.entry(column).or_default();
+
+            column_options.bloom_filter_enabled = Some(true);
+            column_options.bloom_filter_fpp = bloom_filter.fpp;
+            column_options.bloom_filter_ndv = bloom_filter.ndv;
+        }
+
+        options.key_value_metadata.extend(
+            key_value_metadata
+                .into_iter()
+                .map(|(key, value)| (key, Some(value))),
+        );
+
+        options
     }
 }
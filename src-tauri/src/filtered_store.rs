@@ -0,0 +1,261 @@
+//! Module that defines an object store which only lists the files of a location that match
+//! include and exclude glob patterns.
+//! DataFusion lists the files of a listing table on its own, filtering them is thus done by
+//! registering the location of a table behind a filtered store.
+//! Filtered stores are kept by our own [`ObjectStoreRegistry`] so that the stores of dropped
+//! tables can be forgotten
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::SessionState;
+use datafusion::execution::object_store::{DefaultObjectStoreRegistry, ObjectStoreRegistry};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use glob::{MatchOptions, Pattern};
+use object_store::path::Path;
+use object_store::{
+    GetOptions, GetResult, ListResult, MultipartUpload, ObjectMeta, ObjectStore, PutMultipartOpts,
+    PutOptions, PutPayload, PutResult,
+};
+use url::Url;
+
+/// Scheme of the urls under which filtered stores are registered
+const SCHEME: &str = "lens-filtered";
+
+/// An [`ObjectStore`] that hides the objects of a location that do not match its patterns
+#[derive(Debug)]
+struct FilteredStore {
+    inner: Arc<dyn ObjectStore>,
+
+    /// Location whose objects are filtered, patterns are matched against paths relative to it
+    prefix: Path,
+
+    /// Objects must match one of these patterns, if any
+    include: Vec<Pattern>,
+
+    /// Objects must not match any of these patterns
+    exclude: Vec<Pattern>,
+}
+
+impl FilteredStore {
+    fn is_listed(&self, location: &Path) -> bool {
+        let Some(parts) = location.prefix_match(&self.prefix) else {
+            return false;
+        };
+        let relative = parts.map(|p| p.as_ref().to_string()).collect::<Vec<_>>();
+        let relative = relative.join("/");
+
+        // Wildcards do not cross directories, `**` must be used to match nested files
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let matches = |p: &Pattern| p.matches_with(&relative, options);
+
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+impl fmt::Display for FilteredStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Filtered({})", self.inner)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FilteredStore {
+    async fn put_opts(
+        &self,
+        location: &Path,
+        payload: PutPayload,
+        opts: PutOptions,
+    ) -> object_store::Result<PutResult> {
+        self.inner.put_opts(location, payload, opts).await
+    }
+
+    async fn put_multipart_opts(
+        &self,
+        location: &Path,
+        opts: PutMultipartOpts,
+    ) -> object_store::Result<Box<dyn MultipartUpload>> {
+        self.inner.put_multipart_opts(location, opts).await
+    }
+
+    async fn get_opts(
+        &self,
+        location: &Path,
+        options: GetOptions,
+    ) -> object_store::Result<GetResult> {
+        self.inner.get_opts(location, options).await
+    }
+
+    async fn get_range(&self, location: &Path, range: Range<usize>) -> object_store::Result<Bytes> {
+        self.inner.get_range(location, range).await
+    }
+
+    async fn get_ranges(
+        &self,
+        location: &Path,
+        ranges: &[Range<usize>],
+    ) -> object_store::Result<Vec<Bytes>> {
+        self.inner.get_ranges(location, ranges).await
+    }
+
+    async fn head(&self, location: &Path) -> object_store::Result<ObjectMeta> {
+        self.inner.head(location).await
+    }
+
+    async fn delete(&self, location: &Path) -> object_store::Result<()> {
+        self.inner.delete(location).await
+    }
+
+    fn list(&self, prefix: Option<&Path>) -> BoxStream<'_, object_store::Result<ObjectMeta>> {
+        self.inner
+            .list(prefix)
+            .filter(|meta| {
+                let listed = match meta {
+                    Ok(meta) => self.is_listed(&meta.location),
+                    Err(_) => true,
+                };
+                futures::future::ready(listed)
+            })
+            .boxed()
+    }
+
+    async fn list_with_delimiter(&self, prefix: Option<&Path>) -> object_store::Result<ListResult> {
+        let mut result = self.inner.list_with_delimiter(prefix).await?;
+        result.objects.retain(|meta| self.is_listed(&meta.location));
+        Ok(result)
+    }
+
+    async fn copy(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn copy_if_not_exists(&self, from: &Path, to: &Path) -> object_store::Result<()> {
+        self.inner.copy_if_not_exists(from, to).await
+    }
+}
+
+/// An [`ObjectStoreRegistry`] whose filtered stores can be deregistered, DataFusion's default
+/// registry can not forget a store once it has been registered. Other stores are kept by the
+/// default registry
+#[derive(Debug, Default)]
+pub(crate) struct StoreRegistry {
+    inner: DefaultObjectStoreRegistry,
+
+    /// Filtered stores, by identifier
+    filtered: RwLock<HashMap<String, Arc<dyn ObjectStore>>>,
+}
+
+impl StoreRegistry {
+    /// Deregister the filtered stores whose identifier is not in `used`
+    pub(crate) fn retain_filtered(&self, used: &HashSet<String>) {
+        self.filtered
+            .write()
+            .expect("lock poisoned")
+            .retain(|id, _| used.contains(id));
+    }
+}
+
+impl ObjectStoreRegistry for StoreRegistry {
+    fn register_store(
+        &self,
+        url: &Url,
+        store: Arc<dyn ObjectStore>,
+    ) -> Option<Arc<dyn ObjectStore>> {
+        if url.scheme() != SCHEME {
+            return self.inner.register_store(url, store);
+        }
+
+        self.filtered
+            .write()
+            .expect("lock poisoned")
+            .insert(url.host_str().unwrap_or_default().to_string(), store)
+    }
+
+    fn get_store(&self, url: &Url) -> Result<Arc<dyn ObjectStore>> {
+        if url.scheme() != SCHEME {
+            return self.inner.get_store(url);
+        }
+
+        self.filtered
+            .read()
+            .expect("lock poisoned")
+            .get(url.host_str().unwrap_or_default())
+            .cloned()
+            .ok_or_else(|| {
+                DataFusionError::Execution(format!(
+                    "No filtered store found for {url}, its table has been dropped"
+                ))
+            })
+    }
+}
+
+/// Identifier of the store that filters the files of `table_path` with the `include` and
+/// `exclude` patterns.
+/// The identifier is derived from the location and its patterns, so that the store is replaced
+/// instead of piling up when the same table is created again or refreshed
+pub(crate) fn store_id(table_path: &ListingTableUrl, include: &str, exclude: &str) -> String {
+    let table_url: &Url = table_path.as_ref();
+    let mut hasher = DefaultHasher::new();
+    (table_url.as_str(), include, exclude).hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Parse comma separated glob patterns
+fn parse_patterns(patterns: &str) -> Result<Vec<Pattern>> {
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| Pattern::new(p).map_err(|e| DataFusionError::External(Box::new(e))))
+        .collect()
+}
+
+/// Register a store that filters the files of `table_path` with the comma separated `include`
+/// and `exclude` glob patterns, and return the url of the filtered location.
+/// The table path is returned as-is when there are no patterns
+pub(crate) fn register(
+    state: &SessionState,
+    table_path: ListingTableUrl,
+    include: &str,
+    exclude: &str,
+) -> Result<ListingTableUrl> {
+    // Every filtered location gets its own store, identified by the host of its url
+    let id = store_id(&table_path, include, exclude);
+
+    let include = parse_patterns(include)?;
+    let exclude = parse_patterns(exclude)?;
+    if include.is_empty() && exclude.is_empty() {
+        return Ok(table_path);
+    }
+
+    let store = FilteredStore {
+        inner: state.runtime_env().object_store(&table_path)?,
+        prefix: table_path.prefix().clone(),
+        include,
+        exclude,
+    };
+
+    let mut url = Url::parse(&format!("{SCHEME}://{id}"))
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+    let table_url: &Url = table_path.as_ref();
+    url.set_path(table_url.path());
+
+    state
+        .runtime_env()
+        .register_object_store(&url, Arc::new(store));
+
+    ListingTableUrl::parse(url.as_str())
+}
@@ -1,21 +1,53 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::sync::{Arc, Mutex};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
+use bytes::Buf;
 use datafusion::{
-    common::{sql_datafusion_err, DataFusionError},
-    logical_expr::{DdlStatement, LogicalPlan},
+    arrow::json::reader::{infer_json_schema_from_iterator, ReaderBuilder},
+    catalog::{CatalogProviderList, SchemaProvider},
+    catalog_common::{MemoryCatalogProvider, MemorySchemaProvider},
+    common::{sql_datafusion_err, Constraints, DFSchema, DataFusionError, SchemaReference},
+    datasource::{
+        file_format::file_compression_type::FileCompressionType, listing::ListingTableUrl,
+    },
+    execution::{
+        context::SessionState,
+        object_store::ObjectStoreRegistry,
+        runtime_env::{RuntimeConfig, RuntimeEnv},
+        session_state::SessionStateBuilder,
+    },
+    logical_expr::{CreateExternalTable, DdlStatement, LogicalPlan},
     prelude::*,
     sql::{parser::Statement, sqlparser::parser::ParserError, TableReference},
 };
-use object_store::{aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, ObjectStore};
+use futures::{StreamExt, TryStreamExt};
+use object_store::{aws::AmazonS3Builder, gcp::GoogleCloudStorageBuilder, path::Path, ObjectStore};
 use tokio::sync::mpsc;
 use url::Url;
 
 use crate::{
-    common::{DatasourceConfig, ExportOptions, ObjectStoreConfig, Row, StreamId, StreamInfo},
+    catalog::CatalogList,
+    common::{
+        CopyOptions, CreateTableOptions, DatasourceConfig, ExportOptions, FileCompression,
+        FilePreview, ObjectInfo, ObjectPage, ObjectStoreConfig, Row, StreamId, StreamInfo,
+        TableFormat,
+    },
+    filtered_store::StoreRegistry,
     query::stream::{QueryStreamRequest, QueryStreamer},
+    table_provider,
 };
 
+/// Number of entries of a page of objects, when not set
+const DEFAULT_PAGE_SIZE: usize = 1000;
+
+/// Number of rows read to preview a file, when not set
+const DEFAULT_PREVIEW_ROWS: usize = 100;
+
+/// Number of bytes read from the beginning of a JSON file to tell whether it holds an array
+const JSON_HEAD_BYTES: usize = 64 * 1024;
+
 pub struct LensError(anyhow::Error);
 pub type LensResult<T, E = LensError> = std::result::Result<T, E>;
 
@@ -24,6 +56,14 @@ pub struct Lens {
     stream_tx: mpsc::Sender<QueryStreamRequest>,
 
     datasources: Arc<Mutex<Vec<DatasourceConfig>>>,
+
+    catalogs: Arc<CatalogList>,
+
+    /// Registry of the object stores, which forgets the filtered stores of dropped tables
+    stores: Arc<StoreRegistry>,
+
+    /// Definitions of the external tables, by fully qualified name, used to recreate them
+    tables: Arc<Mutex<HashMap<TableReference, CreateExternalTable>>>,
 }
 
 impl<E> From<E> for LensError
@@ -82,7 +122,21 @@ impl Lens {
             .with_create_default_catalog_and_schema(true)
             .with_default_catalog_and_schema("lens", "default");
 
-        let ctx = SessionContext::new_with_config(config);
+        let catalogs = Arc::new(CatalogList::default());
+        let stores = Arc::new(StoreRegistry::default());
+        let runtime = RuntimeEnv::new(
+            RuntimeConfig::new()
+                .with_object_store_registry(Arc::clone(&stores) as Arc<dyn ObjectStoreRegistry>),
+        )
+        .expect("the default runtime configuration should be valid");
+        let state = SessionStateBuilder::new()
+            .with_config(config)
+            .with_runtime_env(Arc::new(runtime))
+            .with_catalog_list(Arc::clone(&catalogs) as Arc<dyn CatalogProviderList>)
+            .with_default_features()
+            .build();
+
+        let ctx = SessionContext::new_with_state(state);
 
         // Replace default table factories by our own factory
         {
@@ -99,6 +153,9 @@ impl Lens {
                 ctx,
                 stream_tx: query_tx,
                 datasources: Arc::new(Mutex::new(vec![])),
+                catalogs,
+                stores,
+                tables: Arc::new(Mutex::new(HashMap::new())),
             },
             query_exec,
         )
@@ -106,11 +163,67 @@ impl Lens {
 
     pub async fn sql(&self, query: &str) -> LensResult<DataFrame> {
         let query = unescape(query)?;
+        let plan = self.create_logical_plan(&query).await?;
 
-        Ok(self
-            .ctx
-            .execute_logical_plan(self.create_logical_plan(&query).await?)
-            .await?)
+        // Keep track of the external tables created or dropped by the statement
+        let ddl = match &plan {
+            LogicalPlan::Ddl(ddl) => Some(ddl.clone()),
+            _ => None,
+        };
+        // The definition of a created table is kept beforehand so that its filtered stores are
+        // not forgotten while it is being created
+        let created = match &ddl {
+            Some(DdlStatement::CreateExternalTable(cmd))
+                if !self.ctx.table_exist(cmd.name.clone())? =>
+            {
+                let mut cmd = cmd.clone();
+                cmd.name = self.resolve(cmd.name);
+                let name = cmd.name.clone();
+                self.tables
+                    .lock()
+                    .expect("lock poisoned")
+                    .insert(name.clone(), cmd);
+                Some(name)
+            }
+            _ => None,
+        };
+
+        let result = self.ctx.execute_logical_plan(plan).await;
+
+        let mut tables = self.tables.lock().expect("lock poisoned");
+        let df = match result {
+            Ok(df) => df,
+            Err(e) => {
+                if let Some(name) = created {
+                    tables.remove(&name);
+                    self.prune_filtered_stores(&tables);
+                }
+                return Err(e.into());
+            }
+        };
+
+        match ddl {
+            Some(DdlStatement::DropTable(cmd)) => {
+                tables.remove(&self.resolve(cmd.name));
+            }
+            Some(DdlStatement::DropCatalogSchema(cmd)) => {
+                let (catalog, schema) = match cmd.name {
+                    SchemaReference::Bare { schema } => {
+                        (self.default_catalog(), schema.to_string())
+                    }
+                    SchemaReference::Full { schema, catalog } => {
+                        (catalog.to_string(), schema.to_string())
+                    }
+                };
+                tables.retain(|t, _| {
+                    t.catalog() != Some(catalog.as_str()) || t.schema() != Some(schema.as_str())
+                });
+            }
+            _ => {}
+        }
+        self.prune_filtered_stores(&tables);
+
+        Ok(df)
     }
 
     pub async fn stream(&self, query: &str) -> LensResult<StreamId> {
@@ -131,14 +244,34 @@ impl Lens {
     pub async fn stream_export(
         &self,
         stream_id: StreamId,
-        options: ExportOptions,
+        mut options: ExportOptions,
     ) -> LensResult<usize> {
+        if let Some(name) = options.datasource.take() {
+            let datasource = self.datasource(&name)?;
+
+            options.path = format!(
+                "{}/{}",
+                datasource.url.trim_end_matches('/'),
+                options.path.trim_start_matches('/')
+            );
+        }
+
         let (req, rx) = QueryStreamRequest::export(stream_id, options);
         self.stream_tx.send(req).await?;
         let count = rx.await?;
         count
     }
 
+    pub async fn stream_copy(
+        &self,
+        stream_id: StreamId,
+        options: CopyOptions,
+    ) -> LensResult<String> {
+        let (req, rx) = QueryStreamRequest::copy(stream_id, options);
+        self.stream_tx.send(req).await?;
+        rx.await?
+    }
+
     pub async fn stream_close(&self, id: StreamId) -> LensResult<()> {
         let (req, rx) = QueryStreamRequest::close(id);
         self.stream_tx.send(req).await?;
@@ -180,6 +313,613 @@ impl Lens {
         self.datasources.lock().expect("lock poisoned").clone()
     }
 
+    /// Find a registered data source by name
+    fn datasource(&self, name: &str) -> LensResult<DatasourceConfig> {
+        Ok(self
+            .datasources()
+            .into_iter()
+            .find(|c| c.is_named(name))
+            .with_context(|| format!("unknown datasource {name}"))?)
+    }
+
+    /// Return the object store of a data source along with its url and the location of its root
+    fn datasource_root(&self, name: &str) -> LensResult<(Arc<dyn ObjectStore>, String, Path)> {
+        let datasource = self.datasource(name)?;
+        let url = Url::parse(&datasource.url)
+            .with_context(|| format!("invalid url {}", datasource.url))?;
+        let store = self
+            .ctx
+            .runtime_env()
+            .object_store(ListingTableUrl::parse(&datasource.url)?)?;
+        let root = Path::from_url_path(url.path())?;
+
+        Ok((
+            store,
+            datasource.url.trim_end_matches('/').to_string(),
+            root,
+        ))
+    }
+
+    /// Location of `path`, relative to the `root` of a data source
+    fn join_root(root: &Path, path: &str) -> Path {
+        Path::from(format!("{root}/{path}"))
+    }
+
+    /// Path of `location` relative to the `root` of a data source
+    fn strip_root(root: &Path, location: &Path) -> String {
+        match location.prefix_match(root) {
+            Some(parts) => Path::from_iter(parts).to_string(),
+            None => location.to_string(),
+        }
+    }
+
+    /// List the objects of a data source under `prefix`, one level at a time when `delimiter` is
+    /// set, in which case deeper objects are grouped by common prefix.
+    /// Paths are relative to the url of the data source. Entries are sorted by path and paged
+    /// with `page_token` being the last path of the previous page
+    pub async fn list_objects(
+        &self,
+        datasource: &str,
+        prefix: &str,
+        delimiter: bool,
+        page_token: Option<String>,
+        page_size: Option<usize>,
+    ) -> LensResult<ObjectPage> {
+        let (store, base_url, root) = self.datasource_root(datasource)?;
+        let prefix = Self::join_root(&root, prefix);
+        let list_prefix = (!prefix.as_ref().is_empty()).then(|| &prefix);
+
+        // Common prefixes and objects are paged together, stores do not all list in order
+        let mut entries = if delimiter {
+            let listing = store.list_with_delimiter(list_prefix).await?;
+            listing
+                .common_prefixes
+                .into_iter()
+                .map(|location| (location, None))
+                .chain(
+                    listing
+                        .objects
+                        .into_iter()
+                        .map(|object| (object.location.clone(), Some(object))),
+                )
+                .collect::<Vec<_>>()
+        } else {
+            store
+                .list(list_prefix)
+                .map_ok(|object| (object.location.clone(), Some(object)))
+                .try_collect::<Vec<_>>()
+                .await?
+        };
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Entries up to the token have been returned by the previous pages
+        let start = page_token.map_or(0, |token| {
+            let offset = Self::join_root(&root, &token);
+            entries.partition_point(|(location, _)| *location <= offset)
+        });
+        let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+        let end = entries.len().min(start.saturating_add(page_size));
+        let next_page_token =
+            (end < entries.len()).then(|| Self::strip_root(&root, &entries[end - 1].0));
+
+        let mut page = ObjectPage {
+            base_url,
+            prefixes: vec![],
+            objects: vec![],
+            next_page_token,
+        };
+        for (location, object) in entries.drain(start..end) {
+            let path = Self::strip_root(&root, &location);
+            match object {
+                Some(object) => {
+                    let mut info = ObjectInfo::from(object);
+                    info.path = path;
+                    page.objects.push(info);
+                }
+                None => page.prefixes.push(path),
+            }
+        }
+
+        Ok(page)
+    }
+
+    pub async fn head_object(&self, datasource: &str, path: &str) -> LensResult<ObjectInfo> {
+        let (store, _, root) = self.datasource_root(datasource)?;
+        let object = store.head(&Self::join_root(&root, path)).await?;
+
+        let mut info = ObjectInfo::from(object);
+        info.path = path.trim_matches('/').to_string();
+        Ok(info)
+    }
+
+    pub async fn create_table(&self, options: CreateTableOptions) -> LensResult<()> {
+        let state = self.ctx.state();
+        let defaults = &state.config().options().catalog;
+        let catalog_name = options
+            .catalog
+            .unwrap_or_else(|| defaults.default_catalog.clone());
+        let schema_name = options
+            .schema
+            .unwrap_or_else(|| defaults.default_schema.clone());
+
+        if options.name.is_empty() {
+            return Err(anyhow!("the name of the table can not be empty").into());
+        }
+        // The name is normalized like the names given to the other table commands, unquoted
+        // names are lowercased
+        let name = match TableReference::parse_str(&options.name) {
+            TableReference::Bare { table } => table.to_string(),
+            _ => {
+                return Err(anyhow!(
+                    "the name of table {} can not be qualified, quote it if it contains dots",
+                    options.name
+                )
+                .into())
+            }
+        };
+        if options.locations.is_empty() {
+            return Err(anyhow!("table {name} must have at least one location").into());
+        }
+        if let TableFormat::Csv(csv) = &options.format {
+            let chars = [Some(csv.delimiter), Some(csv.quote), csv.escape];
+            if chars.iter().flatten().any(|c| !c.is_ascii()) {
+                return Err(
+                    anyhow!("CSV delimiter, quote and escape characters must be ASCII").into(),
+                );
+            }
+        }
+
+        let schema = self.schema_provider(&catalog_name, &schema_name)?;
+
+        let table_ref =
+            TableReference::full(catalog_name.as_str(), schema_name.as_str(), name.as_str());
+        if schema.table_exist(&name) {
+            if options.if_not_exists {
+                return Ok(());
+            }
+            return Err(anyhow!("table {table_ref} already exists").into());
+        }
+
+        for location in &options.locations {
+            Self::check_location(&state, location).await?;
+        }
+
+        // Locations can contain commas, additional locations are given as a JSON array
+        let (location, additional) = options
+            .locations
+            .split_first()
+            .expect("locations have been checked to not be empty");
+        let mut cmd = Self::external_table(table_ref.clone(), location.clone(), &options.format);
+        if !additional.is_empty() {
+            cmd.options.insert(
+                "lens.locations".to_string(),
+                serde_json::to_string(additional)?,
+            );
+        }
+        // Tables created from the application discover their partitions when none are given
+        if options.partition_by.is_empty() {
+            cmd.options
+                .insert("lens.discover_partitions".to_string(), "true".to_string());
+        }
+        cmd.table_partition_cols = options.partition_by;
+        cmd.if_not_exists = options.if_not_exists;
+        if !options.include.is_empty() {
+            cmd.options
+                .insert("lens.include".to_string(), options.include.join(","));
+        }
+        if !options.exclude.is_empty() {
+            cmd.options
+                .insert("lens.exclude".to_string(), options.exclude.join(","));
+        }
+
+        // The table is only registered once its files have been successfully read. Its definition
+        // is kept beforehand so that its filtered stores are not forgotten in the meantime
+        self.tables
+            .lock()
+            .expect("lock poisoned")
+            .insert(table_ref.clone(), cmd.clone());
+        let result: LensResult<()> = async {
+            let table = table_provider::factory().create(&state, &cmd).await?;
+            if table.schema().fields().is_empty() {
+                return Err(anyhow!(
+                    "no column could be read from the files of table {table_ref}, check its format"
+                )
+                .into());
+            }
+
+            schema.register_table(name, table)?;
+            Ok(())
+        }
+        .await;
+
+        if result.is_err() {
+            let mut tables = self.tables.lock().expect("lock poisoned");
+            tables.remove(&table_ref);
+            self.prune_filtered_stores(&tables);
+        }
+
+        result
+    }
+
+    pub fn drop_table(&self, table: &str, if_exists: bool) -> LensResult<()> {
+        let table_ref = self.resolve(TableReference::parse_str(table));
+        let (catalog, schema, name) = Self::parts(&table_ref);
+
+        let dropped = self
+            .schema_provider(catalog, schema)?
+            .deregister_table(name)?;
+        if dropped.is_none() && !if_exists {
+            return Err(anyhow!("unknown table {table_ref}").into());
+        }
+
+        let mut tables = self.tables.lock().expect("lock poisoned");
+        tables.remove(&table_ref);
+        self.prune_filtered_stores(&tables);
+
+        Ok(())
+    }
+
+    /// Rename a table, `new_name` can be qualified to move the table to another schema
+    pub fn rename_table(&self, table: &str, new_name: &str) -> LensResult<()> {
+        let from = self.resolve(TableReference::parse_str(table));
+        let to = self.resolve(TableReference::parse_str(new_name));
+        let (from_catalog, from_schema, from_name) = Self::parts(&from);
+        let (to_catalog, to_schema, to_name) = Self::parts(&to);
+
+        let source = self.schema_provider(from_catalog, from_schema)?;
+        let target = self.schema_provider(to_catalog, to_schema)?;
+        if !source.table_exist(from_name) {
+            return Err(anyhow!("unknown table {from}").into());
+        }
+        if target.table_exist(to_name) {
+            return Err(anyhow!("table {to} already exists").into());
+        }
+
+        let provider = source
+            .deregister_table(from_name)?
+            .with_context(|| format!("unknown table {from}"))?;
+        if let Err(e) = target.register_table(to_name.to_string(), Arc::clone(&provider)) {
+            // Put the table back where it was so that it is not lost
+            source.register_table(from_name.to_string(), provider)?;
+            return Err(e.into());
+        }
+
+        let mut tables = self.tables.lock().expect("lock poisoned");
+        if let Some(mut cmd) = tables.remove(&from) {
+            cmd.name = to.clone();
+            tables.insert(to, cmd);
+        }
+
+        Ok(())
+    }
+
+    /// Recreate a table from its definition, so that the files and schema changes made since it
+    /// was created are taken into account
+    pub async fn refresh_table(&self, table: &str) -> LensResult<()> {
+        let table_ref = self.resolve(TableReference::parse_str(table));
+        let (catalog, schema, name) = Self::parts(&table_ref);
+
+        let cmd = self
+            .tables
+            .lock()
+            .expect("lock poisoned")
+            .get(&table_ref)
+            .cloned()
+            .with_context(|| format!("table {table_ref} is not backed by files"))?;
+
+        let provider = table_provider::factory()
+            .create(&self.ctx.state(), &cmd)
+            .await?;
+
+        let schema = self.schema_provider(catalog, schema)?;
+        schema.deregister_table(name)?;
+        schema.register_table(name.to_string(), provider)?;
+
+        Ok(())
+    }
+
+    pub fn create_schema(
+        &self,
+        catalog: Option<String>,
+        name: &str,
+        if_not_exists: bool,
+    ) -> LensResult<()> {
+        let catalog_name = catalog.unwrap_or_else(|| self.default_catalog());
+        let catalog = self
+            .ctx
+            .catalog(&catalog_name)
+            .with_context(|| format!("unknown catalog {catalog_name}"))?;
+
+        if catalog.schema(name).is_some() {
+            if if_not_exists {
+                return Ok(());
+            }
+            return Err(anyhow!("schema {catalog_name}.{name} already exists").into());
+        }
+
+        catalog.register_schema(name, Arc::new(MemorySchemaProvider::new()))?;
+        Ok(())
+    }
+
+    /// Drop a schema, which must be empty unless `cascade` is set
+    pub fn drop_schema(
+        &self,
+        catalog: Option<String>,
+        name: &str,
+        cascade: bool,
+    ) -> LensResult<()> {
+        let catalog_name = catalog.unwrap_or_else(|| self.default_catalog());
+        if catalog_name == self.default_catalog() && name == self.default_schema() {
+            return Err(
+                anyhow!("the default schema {catalog_name}.{name} can not be dropped").into(),
+            );
+        }
+
+        let catalog = self
+            .ctx
+            .catalog(&catalog_name)
+            .with_context(|| format!("unknown catalog {catalog_name}"))?;
+        catalog
+            .deregister_schema(name, cascade)?
+            .with_context(|| format!("unknown schema {catalog_name}.{name}"))?;
+
+        let mut tables = self.tables.lock().expect("lock poisoned");
+        tables
+            .retain(|t, _| t.catalog() != Some(catalog_name.as_str()) || t.schema() != Some(name));
+        self.prune_filtered_stores(&tables);
+
+        Ok(())
+    }
+
+    pub fn create_catalog(&self, name: &str, if_not_exists: bool) -> LensResult<()> {
+        if self.ctx.catalog(name).is_some() {
+            if if_not_exists {
+                return Ok(());
+            }
+            return Err(anyhow!("catalog {name} already exists").into());
+        }
+
+        self.ctx
+            .register_catalog(name, Arc::new(MemoryCatalogProvider::new()));
+        Ok(())
+    }
+
+    /// Drop a catalog, which must be empty unless `cascade` is set
+    pub fn drop_catalog(&self, name: &str, cascade: bool) -> LensResult<()> {
+        if name == self.default_catalog() {
+            return Err(anyhow!("the default catalog {name} can not be dropped").into());
+        }
+
+        let catalog = self
+            .ctx
+            .catalog(name)
+            .with_context(|| format!("unknown catalog {name}"))?;
+        let schemas = catalog.schema_names();
+        if !cascade && !schemas.is_empty() {
+            return Err(anyhow!(
+                "catalog {name} is not empty, it holds schemas {}",
+                schemas.join(", ")
+            )
+            .into());
+        }
+
+        self.catalogs.deregister_catalog(name);
+        let mut tables = self.tables.lock().expect("lock poisoned");
+        tables.retain(|t, _| t.catalog() != Some(name));
+        self.prune_filtered_stores(&tables);
+
+        Ok(())
+    }
+
+    fn default_catalog(&self) -> String {
+        self.ctx
+            .state()
+            .config()
+            .options()
+            .catalog
+            .default_catalog
+            .clone()
+    }
+
+    fn default_schema(&self) -> String {
+        self.ctx
+            .state()
+            .config()
+            .options()
+            .catalog
+            .default_schema
+            .clone()
+    }
+
+    /// Qualify a table reference with the default catalog and schema
+    fn resolve(&self, table: TableReference) -> TableReference {
+        let resolved = table.resolve(&self.default_catalog(), &self.default_schema());
+        TableReference::full(resolved.catalog, resolved.schema, resolved.table)
+    }
+
+    fn parts(table: &TableReference) -> (&str, &str, &str) {
+        (
+            table.catalog().unwrap_or_default(),
+            table.schema().unwrap_or_default(),
+            table.table(),
+        )
+    }
+
+    /// Forget the filtered stores that are not used by any of the `tables` anymore
+    fn prune_filtered_stores(&self, tables: &HashMap<TableReference, CreateExternalTable>) {
+        let used = tables
+            .values()
+            .filter_map(|cmd| table_provider::filtered_stores(cmd).ok())
+            .flatten()
+            .collect::<HashSet<_>>();
+        self.stores.retain_filtered(&used);
+    }
+
+    fn schema_provider(&self, catalog: &str, schema: &str) -> LensResult<Arc<dyn SchemaProvider>> {
+        let catalog_provider = self
+            .ctx
+            .catalog(catalog)
+            .with_context(|| format!("unknown catalog {catalog}"))?;
+        Ok(catalog_provider
+            .schema(schema)
+            .with_context(|| format!("unknown schema {catalog}.{schema}"))?)
+    }
+
+    /// Read the schema and the first rows of a file, or of the files of a directory, without
+    /// registering a table. The format is inferred from the extension when not given
+    pub async fn preview_file(
+        &self,
+        path: &str,
+        format: Option<TableFormat>,
+        limit: Option<usize>,
+    ) -> LensResult<FilePreview> {
+        let format = match format {
+            Some(format) => format,
+            None => TableFormat::from_path(path).with_context(|| {
+                format!("the format of {path} can not be inferred from its extension")
+            })?,
+        };
+
+        let state = self.ctx.state();
+        Self::check_location(&state, path).await?;
+
+        // DataFusion only reads newline-delimited JSON, files holding an array of records are
+        // decoded on their own
+        if let TableFormat::Json(json) = &format {
+            if let Some(mut records) = Self::read_json_array(&state, path, json.compression).await?
+            {
+                records.truncate(limit.unwrap_or(DEFAULT_PREVIEW_ROWS));
+                let schema = Arc::new(infer_json_schema_from_iterator(records.iter().map(Ok))?);
+                let mut decoder = ReaderBuilder::new(Arc::clone(&schema))
+                    .with_batch_size(records.len().max(1))
+                    .build_decoder()?;
+                decoder.serialize(&records)?;
+                let rows = match decoder.flush()? {
+                    Some(batch) => Row::from_batch(&batch)?,
+                    None => vec![],
+                };
+
+                return Ok(FilePreview {
+                    format,
+                    schema,
+                    rows,
+                });
+            }
+        }
+
+        let cmd = Self::external_table(TableReference::bare("preview"), path.to_string(), &format);
+        let table = table_provider::factory().create(&state, &cmd).await?;
+        let schema = table.schema();
+
+        let batches = self
+            .ctx
+            .read_table(table)?
+            .limit(0, Some(limit.unwrap_or(DEFAULT_PREVIEW_ROWS)))?
+            .collect()
+            .await?;
+        let mut rows = Vec::new();
+        for batch in &batches {
+            rows.extend(Row::from_batch(batch)?);
+        }
+
+        Ok(FilePreview {
+            format,
+            schema,
+            rows,
+        })
+    }
+
+    /// Definition of an external table of `format`, whose files are found at `location`
+    fn external_table(
+        name: TableReference,
+        location: String,
+        format: &TableFormat,
+    ) -> CreateExternalTable {
+        CreateExternalTable {
+            schema: Arc::new(DFSchema::empty()),
+            name,
+            location,
+            file_type: format.file_type().to_string(),
+            table_partition_cols: vec![],
+            if_not_exists: false,
+            definition: None,
+            order_exprs: vec![],
+            unbounded: false,
+            options: format.table_options(),
+            constraints: Constraints::empty(),
+            column_defaults: HashMap::new(),
+        }
+    }
+
+    /// Make sure that a table location can be read and holds at least one file
+    /// Read the records of the JSON file at `location` if it holds an array of records rather than
+    /// newline-delimited records. Only the beginning of the file is read to tell them apart
+    async fn read_json_array(
+        state: &SessionState,
+        location: &str,
+        compression: FileCompression,
+    ) -> LensResult<Option<Vec<serde_json::Value>>> {
+        let url = ListingTableUrl::parse(location)?;
+        if url.is_collection() {
+            return Ok(None);
+        }
+
+        let store = state.runtime_env().object_store(&url)?;
+        let compression = FileCompressionType::from(compression);
+        let size = store.head(url.prefix()).await?.size;
+        let head = store
+            .get_range(url.prefix(), 0..size.min(JSON_HEAD_BYTES))
+            .await?;
+        let mut reader = compression.convert_read(head.reader())?;
+        let mut buffer = [0; 1024];
+        let first = loop {
+            let len = reader.read(&mut buffer)?;
+            if len == 0 {
+                break None;
+            }
+            if let Some(&byte) = buffer[..len].iter().find(|b| !b.is_ascii_whitespace()) {
+                break Some(byte);
+            }
+        };
+        if first != Some(b'[') {
+            return Ok(None);
+        }
+
+        let content = store.get(url.prefix()).await?.bytes().await?;
+        let records = serde_json::from_reader(compression.convert_read(content.reader())?)
+            .with_context(|| format!("{location} is not an array of JSON records"))?;
+        Ok(Some(records))
+    }
+
+    async fn check_location(state: &SessionState, location: &str) -> LensResult<()> {
+        let url = ListingTableUrl::parse(location)?;
+        let store = state
+            .runtime_env()
+            .object_store(&url)
+            .with_context(|| format!("no datasource is registered for {location}"))?;
+
+        let exists = if url.is_collection() {
+            store
+                .list(Some(url.prefix()))
+                .next()
+                .await
+                .transpose()?
+                .is_some()
+        } else {
+            match store.head(url.prefix()).await {
+                Ok(_) => true,
+                Err(object_store::Error::NotFound { .. }) => false,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        if !exists {
+            return Err(anyhow!("location {location} does not exist or is empty").into());
+        }
+
+        Ok(())
+    }
+
     async fn create_logical_plan(&self, query: &str) -> LensResult<LogicalPlan> {
         // We need to create (and rewrite) our own logical plan instead of directly using `sql`
         // from DataFusion `SessionContext` because unfortunately, DataFusion does not properly
@@ -263,3 +1003,129 @@ impl Lens {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use object_store::{memory::InMemory, PutPayload};
+
+    use super::*;
+
+    /// Lens with a `data` datasource rooted at `memory://bucket/root/` holding `locations`
+    async fn lens_with(locations: &[&str]) -> Lens {
+        let (lens, _) = Lens::new();
+        let store = Arc::new(InMemory::new());
+        for location in locations {
+            store
+                .put(&Path::from(*location), PutPayload::from_static(b"data"))
+                .await
+                .unwrap();
+        }
+
+        lens.ctx
+            .register_object_store(&Url::parse("memory://bucket").unwrap(), store);
+        lens.datasources
+            .lock()
+            .expect("lock poisoned")
+            .push(DatasourceConfig {
+                url: "memory://bucket/root/".to_string(),
+                store: ObjectStoreConfig::GoogleCloudStorage {
+                    service_account_path: None,
+                    service_acccount_key: None,
+                    application_credentials_path: None,
+                    bucket: "bucket".to_string(),
+                },
+                name: Some("data".to_string()),
+            });
+        lens
+    }
+
+    async fn list(
+        lens: &Lens,
+        prefix: &str,
+        delimiter: bool,
+        page_token: Option<String>,
+        page_size: Option<usize>,
+    ) -> ObjectPage {
+        match lens
+            .list_objects("data", prefix, delimiter, page_token, page_size)
+            .await
+        {
+            Ok(page) => page,
+            Err(LensError(e)) => panic!("{e:#}"),
+        }
+    }
+
+    fn paths(page: &ObjectPage) -> Vec<&str> {
+        page.objects.iter().map(|o| o.path.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn lists_one_level_with_a_delimiter() {
+        let lens = lens_with(&[
+            "root/b.csv",
+            "root/a/1.csv",
+            "root/a/2.csv",
+            "root/c/d/3.csv",
+            "other/e.csv",
+        ])
+        .await;
+
+        let page = list(&lens, "", true, None, None).await;
+        assert_eq!(page.base_url, "memory://bucket/root");
+        assert_eq!(page.prefixes, vec!["a", "c"]);
+        assert_eq!(paths(&page), vec!["b.csv"]);
+        assert_eq!(page.next_page_token, None);
+
+        let page = list(&lens, "a", true, None, None).await;
+        assert!(page.prefixes.is_empty());
+        assert_eq!(paths(&page), vec!["a/1.csv", "a/2.csv"]);
+    }
+
+    #[tokio::test]
+    async fn lists_recursively_without_a_delimiter() {
+        let lens = lens_with(&["root/b.csv", "root/a/1.csv", "root/c/d/3.csv"]).await;
+
+        let page = list(&lens, "", false, None, None).await;
+        assert!(page.prefixes.is_empty());
+        assert_eq!(paths(&page), vec!["a/1.csv", "b.csv", "c/d/3.csv"]);
+    }
+
+    #[tokio::test]
+    async fn pages_over_prefixes_and_objects() {
+        let lens = lens_with(&["root/a/1.csv", "root/b.csv", "root/c/2.csv", "root/d.csv"]).await;
+
+        let first = list(&lens, "", true, None, Some(3)).await;
+        assert_eq!(first.prefixes, vec!["a", "c"]);
+        assert_eq!(paths(&first), vec!["b.csv"]);
+        assert_eq!(first.next_page_token.as_deref(), Some("c"));
+
+        let second = list(&lens, "", true, first.next_page_token, Some(3)).await;
+        assert!(second.prefixes.is_empty());
+        assert_eq!(paths(&second), vec!["d.csv"]);
+        assert_eq!(second.next_page_token, None);
+    }
+
+    #[tokio::test]
+    async fn pages_recursive_listings_in_order() {
+        let locations = (0..5)
+            .map(|i| format!("root/dir-{}/file.csv", 4 - i))
+            .collect::<Vec<_>>();
+        let lens = lens_with(&locations.iter().map(String::as_str).collect::<Vec<_>>()).await;
+
+        let mut listed = Vec::new();
+        let mut token = None;
+        loop {
+            let page = list(&lens, "", false, token, Some(2)).await;
+            listed.extend(paths(&page).into_iter().map(str::to_string));
+            token = page.next_page_token;
+            if token.is_none() {
+                break;
+            }
+        }
+
+        let expected = (0..5)
+            .map(|i| format!("dir-{i}/file.csv"))
+            .collect::<Vec<_>>();
+        assert_eq!(listed, expected);
+    }
+}
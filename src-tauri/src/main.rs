@@ -4,9 +4,12 @@
 use lens::Lens;
 
 mod aws;
+mod catalog;
 mod cmd;
 mod common;
+mod filtered_store;
 mod lens;
+mod parquet_metadata;
 mod query;
 mod table_provider;
 
@@ -23,14 +26,29 @@ fn main() {
             cmd::aws::aws_sso_login,
             cmd::aws::list_aws_sso_profiles,
             cmd::create::create_datasource,
+            cmd::create::create_table,
+            cmd::create::create_schema,
+            cmd::create::create_catalog,
+            cmd::drop::drop_table,
+            cmd::drop::drop_schema,
+            cmd::drop::drop_catalog,
+            cmd::file::parquet_metadata,
+            cmd::file::head_object,
+            cmd::file::preview_file,
             cmd::list::list_datasources,
             cmd::list::list_databases,
+            cmd::list::list_objects,
             cmd::sql::sql,
             cmd::sql::sql_stream,
             cmd::sql::sql_next,
             cmd::stream::stream_export,
+            cmd::stream::stream_copy,
             cmd::stream::stream_close,
             cmd::stream::stream_list,
+            cmd::table::table_schema_diff,
+            cmd::table::table_statistics,
+            cmd::table::rename_table,
+            cmd::table::refresh_table,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,120 @@
+//! Module that reads the footer of Parquet files stored in any registered object store
+
+use datafusion::datasource::file_format::parquet::fetch_parquet_metadata;
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::SessionState;
+use datafusion::parquet::file::metadata::{ColumnChunkMetaData, RowGroupMetaData};
+use datafusion::parquet::file::statistics::Statistics;
+use datafusion::parquet::schema::printer::print_schema;
+use datafusion::parquet::schema::types::ColumnDescriptor;
+
+use crate::common::{
+    ParquetChunkStatistics, ParquetColumn, ParquetColumnChunk, ParquetMetadata, ParquetRowGroup,
+};
+
+/// Read the footer of the Parquet file at `path`, which can be any url of a registered store
+pub(crate) async fn read(state: &SessionState, path: &str) -> Result<ParquetMetadata> {
+    let url = ListingTableUrl::parse(path)?;
+    let store = state.runtime_env().object_store(&url)?;
+    let meta = store.head(url.prefix()).await?;
+
+    let metadata = fetch_parquet_metadata(store.as_ref(), &meta, None).await?;
+    let file_metadata = metadata.file_metadata();
+
+    let mut schema = Vec::new();
+    print_schema(&mut schema, file_metadata.schema());
+    let schema = String::from_utf8(schema).map_err(|e| DataFusionError::External(Box::new(e)))?;
+
+    let key_value_metadata = file_metadata
+        .key_value_metadata()
+        .map(|kvs| {
+            kvs.iter()
+                .map(|kv| (kv.key.clone(), kv.value.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(ParquetMetadata {
+        path: meta.location.to_string(),
+        size: meta.size,
+        version: file_metadata.version(),
+        num_rows: file_metadata.num_rows(),
+        created_by: file_metadata.created_by().map(str::to_string),
+        schema,
+        columns: file_metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| column(c))
+            .collect(),
+        row_groups: metadata.row_groups().iter().map(row_group).collect(),
+        key_value_metadata,
+    })
+}
+
+fn column(descriptor: &ColumnDescriptor) -> ParquetColumn {
+    ParquetColumn {
+        path: descriptor.path().string(),
+        physical_type: descriptor.physical_type().to_string(),
+        logical_type: descriptor.logical_type().map(|t| format!("{t:?}")),
+        converted_type: descriptor.converted_type().to_string(),
+        max_definition_level: descriptor.max_def_level(),
+        max_repetition_level: descriptor.max_rep_level(),
+    }
+}
+
+fn row_group(row_group: &RowGroupMetaData) -> ParquetRowGroup {
+    ParquetRowGroup {
+        ordinal: row_group.ordinal(),
+        num_rows: row_group.num_rows(),
+        total_byte_size: row_group.total_byte_size(),
+        compressed_size: row_group.compressed_size(),
+        columns: row_group.columns().iter().map(column_chunk).collect(),
+    }
+}
+
+fn column_chunk(chunk: &ColumnChunkMetaData) -> ParquetColumnChunk {
+    ParquetColumnChunk {
+        path: chunk.column_path().string(),
+        physical_type: chunk.column_type().to_string(),
+        compression: chunk.compression().to_string(),
+        encodings: chunk.encodings().iter().map(|e| e.to_string()).collect(),
+        num_values: chunk.num_values(),
+        compressed_size: chunk.compressed_size(),
+        uncompressed_size: chunk.uncompressed_size(),
+        data_page_offset: chunk.data_page_offset(),
+        dictionary_page_offset: chunk.dictionary_page_offset(),
+        statistics: chunk.statistics().map(statistics),
+        has_column_index: chunk.column_index_offset().is_some(),
+        has_offset_index: chunk.offset_index_offset().is_some(),
+        has_bloom_filter: chunk.bloom_filter_offset().is_some(),
+    }
+}
+
+fn statistics(statistics: &Statistics) -> ParquetChunkStatistics {
+    // Bounds are rendered according to the physical type, byte arrays are most often strings
+    let bytes = |b: &[u8]| String::from_utf8_lossy(b).into_owned();
+    let (min, max) = if statistics.has_min_max_set() {
+        let (min, max) = match statistics {
+            Statistics::Boolean(s) => (s.min().to_string(), s.max().to_string()),
+            Statistics::Int32(s) => (s.min().to_string(), s.max().to_string()),
+            Statistics::Int64(s) => (s.min().to_string(), s.max().to_string()),
+            Statistics::Int96(s) => (s.min().to_string(), s.max().to_string()),
+            Statistics::Float(s) => (s.min().to_string(), s.max().to_string()),
+            Statistics::Double(s) => (s.min().to_string(), s.max().to_string()),
+            Statistics::ByteArray(s) => (bytes(s.min().data()), bytes(s.max().data())),
+            Statistics::FixedLenByteArray(s) => (bytes(s.min().data()), bytes(s.max().data())),
+        };
+        (Some(min), Some(max))
+    } else {
+        (None, None)
+    };
+
+    ParquetChunkStatistics {
+        min,
+        max,
+        null_count: statistics.null_count(),
+        distinct_count: statistics.distinct_count(),
+    }
+}
@@ -0,0 +1,445 @@
+//! Module that defines a format to export data in Avro object container files.
+//! DataFusion can only read Avro files, we thus write the object container ourselves:
+//! the Avro schema is derived from the Arrow schema of the exported data and every
+//! [`RecordBatch`] is encoded as a single block of the container
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, RecordBatch, StringArray};
+use datafusion::arrow::compute::{cast_with_options, CastOptions};
+use datafusion::arrow::datatypes::{
+    DataType, Decimal128Type, Field, Float32Type, Float64Type, Int32Type, Int64Type, SchemaRef,
+    TimeUnit,
+};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::error::{DataFusionError, Result};
+use serde_json::{json, Value};
+
+use crate::common::{AvroCodec, AvroExportOptions};
+
+use super::orchestration::StatefulBatchSerializer;
+use super::sink::{self, Serializer};
+
+/// Magic bytes at the start of an Avro object container file
+const AVRO_MAGIC: &[u8] = b"Obj\x01";
+
+/// Size of the marker that separates the blocks of an object container file
+const SYNC_MARKER_LEN: usize = 16;
+
+impl AvroCodec {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Deflate => "deflate",
+            Self::Snappy => "snappy",
+            Self::Zstandard => "zstandard",
+            Self::Bzip2 => "bzip2",
+            Self::Xz => "xz",
+        }
+    }
+
+    /// Compress the serialized data of a block
+    fn compress(&self, data: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Null => data,
+            Self::Deflate => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            Self::Snappy => {
+                // Snappy blocks are followed by the big-endian CRC32 checksum of the uncompressed data
+                let mut compressed = snap::raw::Encoder::new()
+                    .compress_vec(&data)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+                compressed.extend_from_slice(&crc32fast::hash(&data).to_be_bytes());
+                compressed
+            }
+            Self::Zstandard => zstd::stream::encode_all(data.as_slice(), 0)?,
+            Self::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+            Self::Xz => {
+                let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(&data)?;
+                encoder.finish()?
+            }
+        })
+    }
+}
+
+/// Write a zig-zag encoded variable-length `long`
+fn write_long(buf: &mut Vec<u8>, value: i64) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n & !0x7F != 0 {
+        buf.push(((n & 0x7F) | 0x80) as u8);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+/// Write length-prefixed `bytes`, also used for `string` values
+fn write_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(buf, bytes.len() as i64);
+    buf.extend_from_slice(bytes);
+}
+
+/// Return the minimal big-endian two's-complement representation of a decimal value,
+/// as expected by the Avro `decimal` logical type
+fn decimal_bytes(value: i128) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes
+        .windows(2)
+        .take_while(|w| (w[0] == 0x00 && w[1] & 0x80 == 0) || (w[0] == 0xFF && w[1] & 0x80 != 0))
+        .count();
+    bytes[skip..].to_vec()
+}
+
+/// Avro names must start with a letter or an underscore and only contain
+/// letters, digits and underscores
+fn avro_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => name,
+        _ => format!("_{name}"),
+    }
+}
+
+/// How the values of an Arrow column are encoded in Avro
+#[derive(Debug, Clone, Copy)]
+enum AvroKind {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Decimal,
+}
+
+/// Mapping of an Arrow column to an Avro field
+struct AvroType {
+    kind: AvroKind,
+    /// Schema of the Avro type, without nullability
+    schema: Value,
+    /// Arrow type the column is casted to before being encoded.
+    /// Columns without a target type are encoded with their textual representation
+    cast_to: Option<DataType>,
+}
+
+impl AvroType {
+    fn new(kind: AvroKind, schema: Value, cast_to: DataType) -> Self {
+        Self {
+            kind,
+            schema,
+            cast_to: Some(cast_to),
+        }
+    }
+
+    fn from_arrow(data_type: &DataType) -> Self {
+        match data_type {
+            DataType::Null => Self::new(AvroKind::Null, json!("null"), DataType::Null),
+            DataType::Boolean => Self::new(AvroKind::Boolean, json!("boolean"), DataType::Boolean),
+            DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::UInt8
+            | DataType::UInt16 => Self::new(AvroKind::Int, json!("int"), DataType::Int32),
+            DataType::Int64 | DataType::UInt32 | DataType::UInt64 => {
+                Self::new(AvroKind::Long, json!("long"), DataType::Int64)
+            }
+            DataType::Float16 | DataType::Float32 => {
+                Self::new(AvroKind::Float, json!("float"), DataType::Float32)
+            }
+            DataType::Float64 => Self::new(AvroKind::Double, json!("double"), DataType::Float64),
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+                Self::new(AvroKind::String, json!("string"), DataType::Utf8)
+            }
+            DataType::Binary
+            | DataType::LargeBinary
+            | DataType::BinaryView
+            | DataType::FixedSizeBinary(_) => {
+                Self::new(AvroKind::Bytes, json!("bytes"), DataType::Binary)
+            }
+            DataType::Date32 | DataType::Date64 => Self::new(
+                AvroKind::Int,
+                json!({"type": "int", "logicalType": "date"}),
+                DataType::Date32,
+            ),
+            DataType::Time32(_) => Self::new(
+                AvroKind::Int,
+                json!({"type": "int", "logicalType": "time-millis"}),
+                DataType::Time32(TimeUnit::Millisecond),
+            ),
+            DataType::Time64(_) => Self::new(
+                AvroKind::Long,
+                json!({"type": "long", "logicalType": "time-micros"}),
+                DataType::Time64(TimeUnit::Microsecond),
+            ),
+            DataType::Timestamp(unit, tz) => {
+                let (unit, precision) = match unit {
+                    TimeUnit::Second | TimeUnit::Millisecond => (TimeUnit::Millisecond, "millis"),
+                    TimeUnit::Microsecond | TimeUnit::Nanosecond => {
+                        (TimeUnit::Microsecond, "micros")
+                    }
+                };
+                // Timestamps without a timezone do not represent an instant
+                let logical_type = match tz {
+                    Some(_) => format!("timestamp-{precision}"),
+                    None => format!("local-timestamp-{precision}"),
+                };
+                Self::new(
+                    AvroKind::Long,
+                    json!({"type": "long", "logicalType": logical_type}),
+                    DataType::Timestamp(unit, tz.clone()),
+                )
+            }
+            DataType::Decimal128(precision, scale) => Self::new(
+                AvroKind::Decimal,
+                json!({
+                    "type": "bytes",
+                    "logicalType": "decimal",
+                    "precision": precision,
+                    "scale": scale,
+                }),
+                data_type.clone(),
+            ),
+            // Dictionaries are casted to their value type
+            DataType::Dictionary(_, value_type) => Self::from_arrow(value_type),
+            _ => Self {
+                kind: AvroKind::String,
+                schema: json!("string"),
+                cast_to: None,
+            },
+        }
+    }
+}
+
+/// Derive the JSON Avro schema of a record from an Arrow schema
+fn avro_schema(schema: &SchemaRef, record_name: &str) -> Value {
+    let mut names = HashSet::new();
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let avro_type = AvroType::from_arrow(field.data_type());
+
+            // Sanitized names must still be unique within the record
+            let base = avro_name(field.name());
+            let name = (1..)
+                .map(|n| match n {
+                    1 => base.clone(),
+                    n => format!("{base}_{n}"),
+                })
+                .find(|name| !names.contains(name))
+                .expect("a unique field name should exist");
+            names.insert(name.clone());
+
+            let mut avro_field = json!({"name": name});
+            if field.is_nullable() && !matches!(avro_type.kind, AvroKind::Null) {
+                avro_field["type"] = json!(["null", avro_type.schema]);
+                avro_field["default"] = Value::Null;
+            } else {
+                avro_field["type"] = avro_type.schema;
+            }
+
+            // Keep track of the original name when it is not a valid Avro name
+            if &name != field.name() {
+                avro_field["doc"] = json!(field.name());
+            }
+
+            avro_field
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "type": "record",
+        "name": avro_name(record_name),
+        "fields": fields,
+    })
+}
+
+/// A column of a [`RecordBatch`] prepared to be encoded in Avro
+struct AvroColumn {
+    kind: AvroKind,
+    nullable: bool,
+    array: ArrayRef,
+}
+
+impl AvroColumn {
+    fn try_new(field: &Field, array: &ArrayRef) -> Result<Self> {
+        let AvroType { kind, cast_to, .. } = AvroType::from_arrow(field.data_type());
+
+        let array = match cast_to {
+            Some(data_type) => {
+                // Report values that do not fit in the Avro type (e.g. large UInt64) as errors
+                // instead of silently writing them as null
+                let options = CastOptions {
+                    safe: false,
+                    ..Default::default()
+                };
+                let array = cast_with_options(array, &data_type, &options)?;
+                match kind {
+                    // Temporal values are encoded from their underlying integer representation
+                    AvroKind::Int if data_type.is_temporal() => {
+                        cast_with_options(&array, &DataType::Int32, &options)?
+                    }
+                    AvroKind::Long if data_type.is_temporal() => {
+                        cast_with_options(&array, &DataType::Int64, &options)?
+                    }
+                    _ => array,
+                }
+            }
+            None => {
+                let formatter = ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default())?;
+                let values = (0..array.len())
+                    .map(|idx| {
+                        array
+                            .is_valid(idx)
+                            .then(|| formatter.value(idx).to_string())
+                    })
+                    .collect::<StringArray>();
+                Arc::new(values)
+            }
+        };
+
+        Ok(Self {
+            kind,
+            nullable: field.is_nullable(),
+            array,
+        })
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>, idx: usize) -> Result<()> {
+        if self.nullable && !matches!(self.kind, AvroKind::Null) {
+            // Index of the branch of the ["null", T] union
+            if self.array.is_null(idx) {
+                write_long(buf, 0);
+                return Ok(());
+            }
+            write_long(buf, 1);
+        } else if self.array.is_null(idx) && !matches!(self.kind, AvroKind::Null) {
+            return Err(DataFusionError::Execution(
+                "Found a null value in a non-nullable column".to_string(),
+            ));
+        }
+
+        let array = &self.array;
+        match self.kind {
+            AvroKind::Null => {}
+            AvroKind::Boolean => buf.push(array.as_boolean().value(idx) as u8),
+            AvroKind::Int => write_long(buf, array.as_primitive::<Int32Type>().value(idx) as i64),
+            AvroKind::Long => write_long(buf, array.as_primitive::<Int64Type>().value(idx)),
+            AvroKind::Float => {
+                buf.extend_from_slice(&array.as_primitive::<Float32Type>().value(idx).to_le_bytes())
+            }
+            AvroKind::Double => {
+                buf.extend_from_slice(&array.as_primitive::<Float64Type>().value(idx).to_le_bytes())
+            }
+            AvroKind::Bytes => write_bytes(buf, array.as_binary::<i32>().value(idx)),
+            AvroKind::String => write_bytes(buf, array.as_string::<i32>().value(idx).as_bytes()),
+            AvroKind::Decimal => write_bytes(
+                buf,
+                &decimal_bytes(array.as_primitive::<Decimal128Type>().value(idx)),
+            ),
+        }
+
+        Ok(())
+    }
+}
+
+/// Define a struct for serializing record batches of a single Avro object container file
+struct AvroSerializer {
+    codec: AvroCodec,
+    sync_marker: [u8; SYNC_MARKER_LEN],
+    /// Header of the file, written along with the first block
+    header: Option<Vec<u8>>,
+}
+
+impl AvroSerializer {
+    fn new(schema: SchemaRef, options: &AvroExportOptions) -> Self {
+        let sync_marker = rand::random::<[u8; SYNC_MARKER_LEN]>();
+        let avro_schema = avro_schema(&schema, &options.record_name).to_string();
+
+        let mut header = Vec::with_capacity(avro_schema.len() + 64);
+        header.extend_from_slice(AVRO_MAGIC);
+        // File metadata, encoded as an Avro map with a single block of two entries
+        write_long(&mut header, 2);
+        write_bytes(&mut header, b"avro.schema");
+        write_bytes(&mut header, avro_schema.as_bytes());
+        write_bytes(&mut header, b"avro.codec");
+        write_bytes(&mut header, options.codec.name().as_bytes());
+        write_long(&mut header, 0);
+        header.extend_from_slice(&sync_marker);
+
+        Self {
+            codec: options.codec,
+            sync_marker,
+            header: Some(header),
+        }
+    }
+}
+
+impl StatefulBatchSerializer for AvroSerializer {
+    fn serialize(&mut self, batch: RecordBatch) -> Result<Bytes> {
+        let mut buffer = self.header.take().unwrap_or_default();
+        if batch.num_rows() == 0 {
+            return Ok(Bytes::from(buffer));
+        }
+
+        let schema = batch.schema();
+        let columns = schema
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(field, array)| AvroColumn::try_new(field, array))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut data = Vec::with_capacity(batch.get_array_memory_size());
+        for idx in 0..batch.num_rows() {
+            for column in &columns {
+                column.encode(&mut data, idx)?;
+            }
+        }
+        let data = self.codec.compress(data)?;
+
+        write_long(&mut buffer, batch.num_rows() as i64);
+        write_long(&mut buffer, data.len() as i64);
+        buffer.extend_from_slice(&data);
+        buffer.extend_from_slice(&self.sync_marker);
+
+        Ok(Bytes::from(buffer))
+    }
+
+    fn finish(&mut self) -> Result<Bytes> {
+        Ok(self.header.take().map(Bytes::from).unwrap_or_default())
+    }
+}
+
+pub(crate) fn factory(options: AvroExportOptions) -> Arc<dyn FileFormatFactory> {
+    let get_serializer = move |schema: SchemaRef| {
+        Ok(Box::new(AvroSerializer::new(schema, &options)) as Box<dyn StatefulBatchSerializer>)
+    };
+
+    sink::factory(
+        "Avro",
+        "avro",
+        FileCompressionType::UNCOMPRESSED,
+        Serializer::Stateful(Arc::new(get_serializer)),
+    )
+}
@@ -0,0 +1,162 @@
+//! Module that defines a format to export data in CSV.
+//! We need to define our own format instead of using the default DataFusion CSV format
+//! because the underlying arrow CSV writer does not let us configure every aspect of the output,
+//! like the line terminator
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::common::config_datafusion_err;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::write::BatchSerializer;
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::error::{DataFusionError, Result};
+
+use crate::common::{CsvExportOptions, LineTerminator};
+
+use super::sink::{self, Serializer};
+
+/// Options of the CSV writer, validated from the user-provided [`CsvExportOptions`]
+#[derive(Debug, Clone)]
+struct CsvWriterOptions {
+    delimiter: u8,
+    quote: u8,
+    escape: Option<u8>,
+    has_header: bool,
+    null_value: String,
+    date_format: Option<String>,
+    timestamp_format: Option<String>,
+    timestamp_tz_format: Option<String>,
+    time_format: Option<String>,
+    terminator: LineTerminator,
+}
+
+fn to_byte(name: &str, c: char) -> Result<u8> {
+    u8::try_from(c)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or(config_datafusion_err!(
+            "CSV {name} must be a single ASCII character, got '{c}'"
+        ))
+}
+
+impl TryFrom<CsvExportOptions> for CsvWriterOptions {
+    type Error = DataFusionError;
+
+    fn try_from(options: CsvExportOptions) -> Result<Self> {
+        let CsvExportOptions {
+            delimiter,
+            quote,
+            escape,
+            has_header,
+            null_value,
+            date_format,
+            timestamp_format,
+            timestamp_tz_format,
+            time_format,
+            terminator,
+        } = options;
+
+        Ok(Self {
+            delimiter: to_byte("delimiter", delimiter)?,
+            quote: to_byte("quote", quote)?,
+            escape: escape.map(|c| to_byte("escape", c)).transpose()?,
+            has_header,
+            null_value,
+            date_format,
+            timestamp_format,
+            timestamp_tz_format,
+            time_format,
+            terminator,
+        })
+    }
+}
+
+/// Define a struct for serializing CSV records to a stream
+struct CsvSerializer {
+    options: CsvWriterOptions,
+}
+
+impl CsvSerializer {
+    fn writer(&self) -> csv::Writer<Vec<u8>> {
+        let terminator = match self.options.terminator {
+            LineTerminator::Lf => csv::Terminator::Any(b'\n'),
+            LineTerminator::Crlf => csv::Terminator::CRLF,
+        };
+
+        let mut builder = csv::WriterBuilder::new();
+        builder
+            .delimiter(self.options.delimiter)
+            .quote(self.options.quote)
+            .terminator(terminator);
+
+        if let Some(escape) = self.options.escape {
+            builder.escape(escape).double_quote(false);
+        }
+
+        builder.from_writer(Vec::with_capacity(4096))
+    }
+
+    fn format_options(&self) -> FormatOptions<'_> {
+        FormatOptions::default()
+            .with_null(&self.options.null_value)
+            .with_date_format(self.options.date_format.as_deref())
+            .with_timestamp_format(self.options.timestamp_format.as_deref())
+            .with_timestamp_tz_format(self.options.timestamp_tz_format.as_deref())
+            .with_time_format(self.options.time_format.as_deref())
+    }
+}
+
+fn csv_err(e: csv::Error) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+impl BatchSerializer for CsvSerializer {
+    fn serialize(&self, batch: RecordBatch, initial: bool) -> Result<Bytes> {
+        let mut writer = self.writer();
+
+        if initial && self.options.has_header {
+            let schema = batch.schema();
+            writer
+                .write_record(schema.fields().iter().map(|f| f.name()))
+                .map_err(csv_err)?;
+        }
+
+        let options = self.format_options();
+        let formatters = batch
+            .columns()
+            .iter()
+            .map(|c| ArrayFormatter::try_new(c.as_ref(), &options))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut record = Vec::with_capacity(formatters.len());
+        for row in 0..batch.num_rows() {
+            record.clear();
+            record.extend(formatters.iter().map(|f| f.value(row).to_string()));
+            writer.write_record(&record).map_err(csv_err)?;
+        }
+
+        let buffer = writer
+            .into_inner()
+            .map_err(|e| DataFusionError::IoError(e.into_error()))?;
+        Ok(Bytes::from(buffer))
+    }
+}
+
+pub(crate) fn factory(
+    options: CsvExportOptions,
+    compression: FileCompressionType,
+) -> Result<Arc<dyn FileFormatFactory>> {
+    let serializer = CsvSerializer {
+        options: options.try_into()?,
+    };
+
+    Ok(sink::factory(
+        "CSV",
+        "csv",
+        compression,
+        Serializer::Stateless(Arc::new(serializer)),
+    ))
+}
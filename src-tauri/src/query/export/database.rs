@@ -0,0 +1,394 @@
+//! Module that exports data into a table of a SQLite or DuckDB database file.
+//! Unlike the other export formats, a database is a single local file that can not be written
+//! through an object store, rows are thus inserted directly with the client library of the
+//! database engine
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, RecordBatch, StringArray};
+use datafusion::arrow::compute::{cast_with_options, CastOptions};
+use datafusion::arrow::datatypes::{
+    DataType, Field, Float64Type, Int64Type, Schema, SchemaRef, TimeUnit,
+};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::common::not_impl_err;
+use datafusion::error::{DataFusionError, Result};
+use url::Url;
+
+use crate::common::{DatabaseExportOptions, SqlDialect, WriteMode};
+
+/// Database engine to export data into
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum DatabaseKind {
+    Sqlite,
+    Duckdb,
+}
+
+fn sqlite_err(e: rusqlite::Error) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+fn duckdb_err(e: duckdb::Error) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+/// Cast options that report values that do not fit in the target type instead of
+/// silently replacing them with nulls
+fn cast_options() -> CastOptions<'static> {
+    CastOptions {
+        safe: false,
+        ..Default::default()
+    }
+}
+
+/// Resolve the local path of the database file, either from a plain path or a `file://` URL
+fn local_path(path: &str) -> Result<PathBuf> {
+    match Url::parse(path) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().map_err(|_| {
+            DataFusionError::Configuration(format!("Invalid database file path {path}"))
+        }),
+        // Windows paths such as C:\ are parsed as URLs with a single letter scheme
+        Ok(url) if url.scheme().len() > 1 => not_impl_err!(
+            "Database files can only be written locally, {} is not supported",
+            url.scheme()
+        ),
+        _ => Ok(PathBuf::from(path)),
+    }
+}
+
+fn table_exists_err(table_name: &str) -> DataFusionError {
+    DataFusionError::Execution(format!(
+        "Table {table_name} already exists, use the append or overwrite mode to write to it"
+    ))
+}
+
+/// Build the `CREATE TABLE` statement of `schema` with the column types given by `column_type`
+fn create_table(
+    dialect: SqlDialect,
+    table_name: &str,
+    schema: &Schema,
+    column_type: impl Fn(&DataType) -> String,
+) -> String {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let not_null = if field.is_nullable() { "" } else { " NOT NULL" };
+            format!(
+                "{} {}{not_null}",
+                dialect.quote_identifier(field.name()),
+                column_type(field.data_type())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({columns})",
+        dialect.quote_identifier(table_name)
+    )
+}
+
+/// A column of a [`RecordBatch`] converted to SQLite values
+enum SqliteColumn<'a> {
+    /// Booleans and integers, casted to `Int64`
+    Integer(ArrayRef),
+
+    /// Floating point values, casted to `Float64`
+    Real(ArrayRef),
+
+    /// Binary values, casted to `Binary`
+    Blob(ArrayRef),
+
+    /// Any other value, stored as its textual representation
+    Text(&'a ArrayRef, ArrayFormatter<'a>),
+}
+
+impl<'a> SqliteColumn<'a> {
+    fn try_new(array: &'a ArrayRef, options: &'a FormatOptions<'a>) -> Result<Self> {
+        Ok(match array.data_type() {
+            data_type if data_type.is_integer() || data_type == &DataType::Boolean => {
+                Self::Integer(cast_with_options(array, &DataType::Int64, &cast_options())?)
+            }
+            data_type if data_type.is_floating() => Self::Real(cast_with_options(
+                array,
+                &DataType::Float64,
+                &cast_options(),
+            )?),
+            DataType::Binary
+            | DataType::LargeBinary
+            | DataType::BinaryView
+            | DataType::FixedSizeBinary(_) => Self::Blob(cast_with_options(
+                array,
+                &DataType::Binary,
+                &cast_options(),
+            )?),
+            _ => Self::Text(array, ArrayFormatter::try_new(array.as_ref(), options)?),
+        })
+    }
+
+    fn value(&self, idx: usize) -> rusqlite::types::Value {
+        use rusqlite::types::Value;
+
+        match self {
+            Self::Integer(array) if array.is_valid(idx) => {
+                Value::Integer(array.as_primitive::<Int64Type>().value(idx))
+            }
+            Self::Real(array) if array.is_valid(idx) => {
+                Value::Real(array.as_primitive::<Float64Type>().value(idx))
+            }
+            Self::Blob(array) if array.is_valid(idx) => {
+                Value::Blob(array.as_binary::<i32>().value(idx).to_vec())
+            }
+            Self::Text(array, formatter) if array.is_valid(idx) => {
+                Value::Text(formatter.value(idx).to_string())
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+fn write_sqlite(
+    path: PathBuf,
+    table_name: &str,
+    schema: &Schema,
+    batches: &[RecordBatch],
+    mode: WriteMode,
+) -> Result<usize> {
+    let dialect = SqlDialect::Sqlite;
+    let table = dialect.quote_identifier(table_name);
+
+    let mut conn = rusqlite::Connection::open(path).map_err(sqlite_err)?;
+    let tx = conn.transaction().map_err(sqlite_err)?;
+
+    match mode {
+        WriteMode::ErrorIfExists => {
+            let exists = tx
+                .query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                    [table_name],
+                    |row| row.get::<_, i64>(0),
+                )
+                .map_err(sqlite_err)?
+                > 0;
+            if exists {
+                return Err(table_exists_err(table_name));
+            }
+        }
+        WriteMode::Append => {}
+        WriteMode::Overwrite => {
+            tx.execute_batch(&format!("DROP TABLE IF EXISTS {table}"))
+                .map_err(sqlite_err)?;
+        }
+    }
+    tx.execute_batch(&create_table(dialect, table_name, schema, |data_type| {
+        dialect.column_type(data_type)
+    }))
+    .map_err(sqlite_err)?;
+
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| dialect.quote_identifier(f.name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = (1..=schema.fields().len())
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut count = 0;
+    {
+        let mut stmt = tx
+            .prepare(&format!(
+                "INSERT INTO {table} ({columns}) VALUES ({placeholders})"
+            ))
+            .map_err(sqlite_err)?;
+
+        let format_options = dialect.format_options();
+        for batch in batches {
+            let columns = batch
+                .columns()
+                .iter()
+                .map(|array| SqliteColumn::try_new(array, &format_options))
+                .collect::<Result<Vec<_>>>()?;
+
+            for idx in 0..batch.num_rows() {
+                let values = columns.iter().map(|c| c.value(idx));
+                stmt.execute(rusqlite::params_from_iter(values))
+                    .map_err(sqlite_err)?;
+                count += 1;
+            }
+        }
+    }
+
+    tx.commit().map_err(sqlite_err)?;
+    Ok(count)
+}
+
+/// Return the DuckDB column type of a value of `data_type` along with the Arrow type the column
+/// must be casted to before being appended.
+/// Columns without a target type are stored as their textual representation
+fn duckdb_type(data_type: &DataType) -> (String, Option<DataType>) {
+    let same = |name: &str| (name.to_string(), Some(data_type.clone()));
+
+    match data_type {
+        DataType::Boolean => same("BOOLEAN"),
+        DataType::Int8 => same("TINYINT"),
+        DataType::Int16 => same("SMALLINT"),
+        DataType::Int32 => same("INTEGER"),
+        DataType::Int64 => same("BIGINT"),
+        DataType::UInt8 => same("UTINYINT"),
+        DataType::UInt16 => same("USMALLINT"),
+        DataType::UInt32 => same("UINTEGER"),
+        DataType::UInt64 => same("UBIGINT"),
+        DataType::Float16 | DataType::Float32 => ("FLOAT".to_string(), Some(DataType::Float32)),
+        DataType::Float64 => same("DOUBLE"),
+        DataType::Decimal128(p, s) if *p <= 38 => same(&format!("DECIMAL({p}, {s})")),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+            ("VARCHAR".to_string(), Some(DataType::Utf8))
+        }
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => ("BLOB".to_string(), Some(DataType::Binary)),
+        DataType::Date32 | DataType::Date64 => ("DATE".to_string(), Some(DataType::Date32)),
+        DataType::Time32(_) | DataType::Time64(_) => (
+            "TIME".to_string(),
+            Some(DataType::Time64(TimeUnit::Microsecond)),
+        ),
+        DataType::Timestamp(_, None) => (
+            "TIMESTAMP".to_string(),
+            Some(DataType::Timestamp(TimeUnit::Microsecond, None)),
+        ),
+        DataType::Timestamp(_, tz) => (
+            "TIMESTAMPTZ".to_string(),
+            Some(DataType::Timestamp(TimeUnit::Microsecond, tz.clone())),
+        ),
+        DataType::Dictionary(_, value_type) => duckdb_type(value_type),
+        _ => ("VARCHAR".to_string(), None),
+    }
+}
+
+/// Convert the columns of `batch` to the Arrow types expected by the DuckDB appender
+fn duckdb_batch(batch: &RecordBatch) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let options = FormatOptions::default();
+
+    let (fields, columns): (Vec<_>, Vec<_>) = schema
+        .fields()
+        .iter()
+        .zip(batch.columns())
+        .map(|(field, array)| {
+            let array = match duckdb_type(field.data_type()) {
+                (_, Some(data_type)) => cast_with_options(array, &data_type, &cast_options())?,
+                (_, None) => {
+                    let formatter = ArrayFormatter::try_new(array.as_ref(), &options)?;
+                    let values = (0..array.len())
+                        .map(|idx| {
+                            array
+                                .is_valid(idx)
+                                .then(|| formatter.value(idx).to_string())
+                        })
+                        .collect::<StringArray>();
+                    Arc::new(values) as ArrayRef
+                }
+            };
+
+            let field = Field::new(field.name(), array.data_type().clone(), field.is_nullable());
+            Ok((field, array))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+fn write_duckdb(
+    path: PathBuf,
+    table_name: &str,
+    schema: &Schema,
+    batches: &[RecordBatch],
+    mode: WriteMode,
+) -> Result<usize> {
+    // DuckDB quotes identifiers like Postgres
+    let dialect = SqlDialect::Postgres;
+    let table = dialect.quote_identifier(table_name);
+
+    let mut conn = duckdb::Connection::open(path).map_err(duckdb_err)?;
+    let tx = conn.transaction().map_err(duckdb_err)?;
+
+    match mode {
+        WriteMode::ErrorIfExists => {
+            let exists = tx
+                .query_row(
+                    "SELECT COUNT(*) FROM information_schema.tables \
+                     WHERE table_schema = current_schema() AND table_name = ?",
+                    [table_name],
+                    |row| row.get::<_, i64>(0),
+                )
+                .map_err(duckdb_err)?
+                > 0;
+            if exists {
+                return Err(table_exists_err(table_name));
+            }
+        }
+        WriteMode::Append => {}
+        WriteMode::Overwrite => {
+            tx.execute_batch(&format!("DROP TABLE IF EXISTS {table}"))
+                .map_err(duckdb_err)?;
+        }
+    }
+    tx.execute_batch(&create_table(dialect, table_name, schema, |data_type| {
+        duckdb_type(data_type).0
+    }))
+    .map_err(duckdb_err)?;
+
+    let mut count = 0;
+    {
+        let mut appender = tx.appender(table_name).map_err(duckdb_err)?;
+        for batch in batches {
+            appender
+                .append_record_batch(duckdb_batch(batch)?)
+                .map_err(duckdb_err)?;
+            count += batch.num_rows();
+        }
+        appender.flush().map_err(duckdb_err)?;
+    }
+
+    tx.commit().map_err(duckdb_err)?;
+    Ok(count)
+}
+
+/// Write `batches` into the table of the database file at `path`.
+/// The table is created if it does not exist yet, otherwise it is handled according to `mode`.
+/// Returns the number of rows that have been written
+pub(crate) async fn write(
+    kind: DatabaseKind,
+    path: &str,
+    options: DatabaseExportOptions,
+    schema: SchemaRef,
+    batches: Vec<RecordBatch>,
+    mode: WriteMode,
+) -> Result<usize> {
+    if options.table_name.is_empty() {
+        return Err(DataFusionError::Configuration(
+            "The table name can not be empty".to_string(),
+        ));
+    }
+
+    let path = local_path(path)?;
+
+    // Database clients are blocking
+    tokio::task::spawn_blocking(move || match kind {
+        DatabaseKind::Sqlite => write_sqlite(path, &options.table_name, &schema, &batches, mode),
+        DatabaseKind::Duckdb => write_duckdb(path, &options.table_name, &schema, &batches, mode),
+    })
+    .await
+    .map_err(|e| DataFusionError::External(Box::new(e)))?
+}
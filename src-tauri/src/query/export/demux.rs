@@ -38,10 +38,10 @@ use datafusion::physical_plan::SendableRecordBatchStream;
 use futures::StreamExt;
 use object_store::path::Path;
 
-use rand::distributions::DistString;
-
 use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
 
+use super::FileNaming;
+
 type RecordBatchReceiver = Receiver<RecordBatch>;
 type DemuxedStreamReceiver = UnboundedReceiver<(Path, RecordBatchReceiver)>;
 
@@ -127,7 +127,7 @@ async fn row_count_demuxer(
     let max_buffered_batches = exec_options.max_buffered_batches_per_output_file;
     let minimum_parallel_files = exec_options.minimum_parallel_output_files;
     let mut part_idx = 0;
-    let write_id = rand::distributions::Alphanumeric.sample_string(&mut rand::thread_rng(), 16);
+    let file_naming = FileNaming::new(&context);
 
     let mut open_file_streams = Vec::with_capacity(minimum_parallel_files);
 
@@ -152,7 +152,7 @@ async fn row_count_demuxer(
         if open_file_streams.len() < minimum_parallel_files {
             open_file_streams.push(create_new_file_stream(
                 &base_output_path,
-                &write_id,
+                &file_naming,
                 part_idx,
                 &file_extension,
                 single_file_output,
@@ -165,7 +165,7 @@ async fn row_count_demuxer(
             row_counts[next_send_steam] = 0;
             open_file_streams[next_send_steam] = create_new_file_stream(
                 &base_output_path,
-                &write_id,
+                &file_naming,
                 part_idx,
                 &file_extension,
                 single_file_output,
@@ -190,7 +190,7 @@ async fn row_count_demuxer(
 /// Helper for row count demuxer
 fn generate_file_path(
     base_output_path: &ListingTableUrl,
-    write_id: &str,
+    file_naming: &FileNaming,
     part_idx: usize,
     file_extension: &str,
     single_file_output: bool,
@@ -198,7 +198,7 @@ fn generate_file_path(
     if !single_file_output {
         base_output_path
             .prefix()
-            .child(format!("{}_{}.{}", write_id, part_idx, file_extension))
+            .child(file_naming.file_name(Some(part_idx), file_extension))
     } else {
         base_output_path.prefix().to_owned()
     }
@@ -207,7 +207,7 @@ fn generate_file_path(
 /// Helper for row count demuxer
 fn create_new_file_stream(
     base_output_path: &ListingTableUrl,
-    write_id: &str,
+    file_naming: &FileNaming,
     part_idx: usize,
     file_extension: &str,
     single_file_output: bool,
@@ -216,7 +216,7 @@ fn create_new_file_stream(
 ) -> Result<Sender<RecordBatch>> {
     let file_path = generate_file_path(
         base_output_path,
-        write_id,
+        file_naming,
         part_idx,
         file_extension,
         single_file_output,
@@ -240,7 +240,7 @@ async fn hive_style_partitions_demuxer(
     file_extension: String,
     keep_partition_by_columns: bool,
 ) -> Result<()> {
-    let write_id = rand::distributions::Alphanumeric.sample_string(&mut rand::thread_rng(), 16);
+    let file_naming = FileNaming::new(&context);
 
     let exec_options = &context.session_config().options().execution;
     let max_buffered_recordbatches = exec_options.max_buffered_batches_per_output_file;
@@ -275,7 +275,7 @@ async fn hive_style_partitions_demuxer(
                     let file_path = compute_hive_style_file_path(
                         &part_key,
                         &partition_by,
-                        &write_id,
+                        &file_naming,
                         &file_extension,
                         &base_output_path,
                     );
@@ -410,7 +410,7 @@ fn remove_partition_by_columns(
 fn compute_hive_style_file_path(
     part_key: &[String],
     partition_by: &[(String, DataType)],
-    write_id: &str,
+    file_naming: &FileNaming,
     file_extension: &str,
     base_output_path: &ListingTableUrl,
 ) -> Path {
@@ -419,5 +419,5 @@ fn compute_hive_style_file_path(
         file_path = file_path.child(format!("{}={}", partition_by[j].0, part_key[j]));
     }
 
-    file_path.child(format!("{}.{}", write_id, file_extension))
+    file_path.child(file_naming.file_name(None, file_extension))
 }
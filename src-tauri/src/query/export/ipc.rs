@@ -0,0 +1,102 @@
+//! Module that defines a format to export data in Arrow IPC.
+//! We need to define our own format instead of using the default DataFusion Arrow format
+//! because DataFusion only writes the IPC file format with a fixed buffer compression
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::ipc::writer::{FileWriter, IpcWriteOptions, StreamWriter};
+use datafusion::arrow::ipc::CompressionType;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::error::Result;
+
+use crate::common::{ArrowIpcCompression, ArrowIpcExportOptions, ArrowIpcLayout};
+
+use super::orchestration::StatefulBatchSerializer;
+use super::sink::{self, Serializer};
+use super::SharedBuffer;
+
+impl ArrowIpcLayout {
+    fn ext(&self) -> &'static str {
+        match self {
+            Self::File => "arrow",
+            Self::Stream => "arrows",
+        }
+    }
+}
+
+enum IpcWriter {
+    File(FileWriter<SharedBuffer>),
+    Stream(StreamWriter<SharedBuffer>),
+}
+
+/// Define a struct for serializing record batches of a single file in Arrow IPC
+struct ArrowIpcSerializer {
+    writer: IpcWriter,
+    buffer: SharedBuffer,
+}
+
+impl ArrowIpcSerializer {
+    fn try_new(schema: SchemaRef, options: &ArrowIpcExportOptions) -> Result<Self> {
+        let compression = options.compression.map(|c| match c {
+            ArrowIpcCompression::Lz4 => CompressionType::LZ4_FRAME,
+            ArrowIpcCompression::Zstd => CompressionType::ZSTD,
+        });
+        let write_options = IpcWriteOptions::default().try_with_compression(compression)?;
+
+        let buffer = SharedBuffer::default();
+        let writer = match options.layout {
+            ArrowIpcLayout::File => IpcWriter::File(FileWriter::try_new_with_options(
+                buffer.clone(),
+                &schema,
+                write_options,
+            )?),
+            ArrowIpcLayout::Stream => IpcWriter::Stream(StreamWriter::try_new_with_options(
+                buffer.clone(),
+                &schema,
+                write_options,
+            )?),
+        };
+
+        Ok(Self { writer, buffer })
+    }
+}
+
+impl StatefulBatchSerializer for ArrowIpcSerializer {
+    fn serialize(&mut self, batch: RecordBatch) -> Result<Bytes> {
+        match &mut self.writer {
+            IpcWriter::File(writer) => writer.write(&batch)?,
+            IpcWriter::Stream(writer) => writer.write(&batch)?,
+        }
+
+        Ok(self.buffer.take())
+    }
+
+    fn finish(&mut self) -> Result<Bytes> {
+        match &mut self.writer {
+            IpcWriter::File(writer) => writer.finish()?,
+            IpcWriter::Stream(writer) => writer.finish()?,
+        }
+
+        Ok(self.buffer.take())
+    }
+}
+
+pub(crate) fn factory(options: ArrowIpcExportOptions) -> Arc<dyn FileFormatFactory> {
+    let ext = options.layout.ext();
+    let get_serializer = move |schema: SchemaRef| {
+        ArrowIpcSerializer::try_new(schema, &options)
+            .map(|s| Box::new(s) as Box<dyn StatefulBatchSerializer>)
+    };
+
+    // Buffers are compressed by the IPC writer, files are never compressed as a whole
+    sink::factory(
+        "Arrow IPC",
+        ext,
+        FileCompressionType::UNCOMPRESSED,
+        Serializer::Stateful(Arc::new(get_serializer)),
+    )
+}
@@ -1,224 +1,159 @@
 //! Module that defines a format to export data in JSON.
 //! We need to define our own format instead of using the default DataFusion JSON format
-//! because DataFusion will output one JSON object per-line instead of outputing a JSON array
+//! because DataFusion will output one JSON object per-line instead of outputing a JSON array.
+//! Newline-delimited JSON is also supported and behaves like the default DataFusion format,
+//! with additional options
 
-use std::any::Any;
-use std::fmt;
 use std::sync::Arc;
 
-use async_trait::async_trait;
 use bytes::Bytes;
-use datafusion::arrow::array::RecordBatch;
-use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::arrow::array::{Array, ArrayRef, RecordBatch, StringArray};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use datafusion::arrow::json;
-use datafusion::common::file_options::json_writer::JsonWriterOptions;
-use datafusion::common::{GetExt, Statistics};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
 use datafusion::datasource::file_format::FileFormatFactory;
-use datafusion::datasource::file_format::{
-    file_compression_type::FileCompressionType, json::JsonFormat, FileFormat,
-};
-use datafusion::datasource::physical_plan::{FileScanConfig, FileSinkConfig};
-use datafusion::error::{DataFusionError, Result};
-use datafusion::execution::context::SessionState;
-use datafusion::execution::{SendableRecordBatchStream, TaskContext};
-use datafusion::physical_expr::PhysicalSortRequirement;
-use datafusion::physical_plan::insert::{DataSink, DataSinkExec};
-use datafusion::physical_plan::metrics::MetricsSet;
-use datafusion::physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan, PhysicalExpr};
-use object_store::{ObjectMeta, ObjectStore};
-
-use super::orchestration::stateless_multipart_put;
-
-/// Implementation of a JSON file format based on the original DataFusion [`JsonFormat`] that will
-/// output data as an array instead of object line separated
-#[derive(Debug)]
-struct JsonArrayFormat {
-    inner: JsonFormat,
-}
+use datafusion::error::Result;
 
-#[derive(Debug)]
-struct JsonArrayFormatFactory;
+use crate::common::JsonExportOptions;
 
-impl JsonArrayFormat {
-    fn new() -> Self {
-        Self {
-            inner: JsonFormat::default(),
-        }
-    }
-}
+use super::orchestration::StatefulBatchSerializer;
+use super::sink::{self, Serializer};
+use super::SharedBuffer;
 
-impl GetExt for JsonArrayFormatFactory {
-    fn get_ext(&self) -> String {
-        "json".to_string()
-    }
-}
-
-impl FileFormatFactory for JsonArrayFormatFactory {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    fn create(
-        &self,
-        _state: &SessionState,
-        _format_options: &std::collections::HashMap<String, String>,
-    ) -> Result<Arc<dyn FileFormat>> {
-        Ok(self.default())
-    }
+/// Layout of the JSON documents of exported files
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum JsonLayout {
+    /// Records are written as an array of objects
+    Array,
 
-    fn default(&self) -> Arc<dyn FileFormat> {
-        Arc::new(JsonArrayFormat::new())
-    }
+    /// Records are written as one object per line
+    NewlineDelimited,
 }
 
-#[async_trait]
-impl FileFormat for JsonArrayFormat {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    fn get_ext(&self) -> String {
-        self.inner.get_ext()
-    }
-
-    fn get_ext_with_compression(
-        &self,
-        file_compression_type: &FileCompressionType,
-    ) -> Result<String> {
-        self.inner.get_ext_with_compression(file_compression_type)
-    }
-
-    async fn infer_schema(
-        &self,
-        state: &SessionState,
-        store: &Arc<dyn ObjectStore>,
-        objects: &[ObjectMeta],
-    ) -> Result<SchemaRef> {
-        self.inner.infer_schema(state, store, objects).await
-    }
-
-    async fn infer_stats(
-        &self,
-        state: &SessionState,
-        store: &Arc<dyn ObjectStore>,
-        table_schema: SchemaRef,
-        object: &ObjectMeta,
-    ) -> Result<Statistics> {
-        self.inner
-            .infer_stats(state, store, table_schema, object)
-            .await
-    }
-
-    async fn create_physical_plan(
-        &self,
-        state: &SessionState,
-        conf: FileScanConfig,
-        filters: Option<&Arc<dyn PhysicalExpr>>,
-    ) -> Result<Arc<dyn ExecutionPlan>> {
-        self.inner.create_physical_plan(state, conf, filters).await
-    }
-
-    async fn create_writer_physical_plan(
-        &self,
-        input: Arc<dyn ExecutionPlan>,
-        _state: &SessionState,
-        conf: FileSinkConfig,
-        order_requirements: Option<Vec<PhysicalSortRequirement>>,
-    ) -> Result<Arc<dyn ExecutionPlan>> {
-        if conf.overwrite {
-            return Err(DataFusionError::NotImplemented(
-                "JSON files can not be overwritten yet".to_string(),
-            ));
-        }
-
-        let writer_options = JsonWriterOptions::try_from(self.inner.options())?;
-        let schema = Arc::clone(conf.output_schema());
-        let sink = Arc::new(JsonArraySink::new(conf, writer_options));
-
-        Ok(Arc::new(DataSinkExec::new(input, sink, schema, order_requirements)) as _)
-    }
+/// Writer of a single JSON file, according to its [`JsonLayout`]
+enum JsonWriter {
+    Array(json::ArrayWriter<SharedBuffer>),
+    NewlineDelimited(json::LineDelimitedWriter<SharedBuffer>),
 }
 
-/// Define a struct for serializing Json records to a stream
-pub struct JsonArraySerializer;
-
-impl datafusion::datasource::file_format::write::BatchSerializer for JsonArraySerializer {
-    fn serialize(&self, batch: RecordBatch, _initial: bool) -> Result<Bytes> {
-        let mut buffer = Vec::with_capacity(4096);
-        let mut writer = json::ArrayWriter::new(&mut buffer);
-        writer.write(&batch)?;
-        Ok(Bytes::from(buffer))
-    }
+/// Define a struct for serializing the records of a single file in JSON.
+/// The writer is kept for the whole file so that an array is opened and closed only once
+struct JsonSerializer {
+    writer: JsonWriter,
+    buffer: SharedBuffer,
+    options: JsonExportOptions,
 }
 
-/// Implements [`DataSink`] for writing to a Json file.
-struct JsonArraySink {
-    /// Config options for writing data
-    config: FileSinkConfig,
-    /// Writer options for underlying Json writer
-    writer_options: JsonWriterOptions,
-}
+impl JsonSerializer {
+    fn new(layout: JsonLayout, options: JsonExportOptions) -> Self {
+        let buffer = SharedBuffer::default();
+        let builder = json::WriterBuilder::new().with_explicit_nulls(options.explicit_nulls);
+        let writer = match layout {
+            JsonLayout::Array => JsonWriter::Array(builder.build(buffer.clone())),
+            JsonLayout::NewlineDelimited => {
+                JsonWriter::NewlineDelimited(builder.build(buffer.clone()))
+            }
+        };
 
-impl std::fmt::Debug for JsonArraySink {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("JsonSink").finish()
+        Self {
+            writer,
+            buffer,
+            options,
+        }
     }
-}
 
-impl DisplayAs for JsonArraySink {
-    fn fmt_as(&self, _t: DisplayFormatType, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Ok(())
+    fn should_format(&self, data_type: &DataType) -> bool {
+        match data_type {
+            DataType::Date32 | DataType::Date64 => self.options.date_format.is_some(),
+            DataType::Timestamp(_, None) => self.options.timestamp_format.is_some(),
+            DataType::Timestamp(_, Some(_)) => self.options.timestamp_tz_format.is_some(),
+            DataType::Time32(_) | DataType::Time64(_) => self.options.time_format.is_some(),
+            _ => false,
+        }
     }
-}
 
-impl JsonArraySink {
-    /// Create from config.
-    pub fn new(config: FileSinkConfig, writer_options: JsonWriterOptions) -> Self {
-        Self {
-            config,
-            writer_options,
+    /// The arrow JSON writer does not let us choose how temporal values are formatted.
+    /// Temporal columns that have a custom format are thus converted to strings beforehand
+    fn format_temporal_columns(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let schema = batch.schema();
+        if !schema
+            .fields()
+            .iter()
+            .any(|f| self.should_format(f.data_type()))
+        {
+            return Ok(batch);
         }
-    }
 
-    async fn multipartput_all(
-        &self,
-        data: SendableRecordBatchStream,
-        context: &Arc<TaskContext>,
-    ) -> Result<u64> {
-        let get_serializer = move || Arc::new(JsonArraySerializer) as _;
-
-        stateless_multipart_put(
-            data,
-            context,
-            "json".into(),
-            Box::new(get_serializer),
-            &self.config,
-            self.writer_options.compression.into(),
-        )
-        .await
+        let format_options = FormatOptions::default()
+            .with_date_format(self.options.date_format.as_deref())
+            .with_datetime_format(self.options.date_format.as_deref())
+            .with_timestamp_format(self.options.timestamp_format.as_deref())
+            .with_timestamp_tz_format(self.options.timestamp_tz_format.as_deref())
+            .with_time_format(self.options.time_format.as_deref());
+
+        let (fields, columns): (Vec<_>, Vec<_>) = schema
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .map(|(field, column)| {
+                if !self.should_format(field.data_type()) {
+                    return Ok((Arc::clone(field), Arc::clone(column)));
+                }
+
+                let formatter = ArrayFormatter::try_new(column.as_ref(), &format_options)?;
+                let values = (0..column.len())
+                    .map(|i| column.is_valid(i).then(|| formatter.value(i).to_string()))
+                    .collect::<StringArray>();
+
+                let field = Field::new(field.name(), DataType::Utf8, field.is_nullable());
+                Ok((Arc::new(field), Arc::new(values) as ArrayRef))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+
+        let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+        Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
     }
 }
 
-#[async_trait]
-impl DataSink for JsonArraySink {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
+impl StatefulBatchSerializer for JsonSerializer {
+    fn serialize(&mut self, batch: RecordBatch) -> Result<Bytes> {
+        let batch = self.format_temporal_columns(batch)?;
+        match &mut self.writer {
+            JsonWriter::Array(writer) => writer.write(&batch)?,
+            JsonWriter::NewlineDelimited(writer) => writer.write(&batch)?,
+        }
 
-    fn metrics(&self) -> Option<MetricsSet> {
-        None
+        Ok(self.buffer.take())
     }
 
-    async fn write_all(
-        &self,
-        data: SendableRecordBatchStream,
-        context: &Arc<TaskContext>,
-    ) -> Result<u64> {
-        let total_count = self.multipartput_all(data, context).await?;
-        Ok(total_count)
+    fn finish(&mut self) -> Result<Bytes> {
+        // Closes the array, which is opened by the first record of the file
+        match &mut self.writer {
+            JsonWriter::Array(writer) => writer.finish()?,
+            JsonWriter::NewlineDelimited(writer) => writer.finish()?,
+        }
+
+        Ok(self.buffer.take())
     }
 }
 
-pub(crate) fn factory() -> Arc<dyn FileFormatFactory> {
-    Arc::new(JsonArrayFormatFactory)
+pub(crate) fn factory(
+    layout: JsonLayout,
+    options: JsonExportOptions,
+    compression: FileCompressionType,
+) -> Arc<dyn FileFormatFactory> {
+    let get_serializer = move |_schema: SchemaRef| {
+        Ok(Box::new(JsonSerializer::new(layout, options.clone()))
+            as Box<dyn StatefulBatchSerializer>)
+    };
+
+    sink::factory(
+        "JSON",
+        "json",
+        compression,
+        Serializer::Stateful(Arc::new(get_serializer)),
+    )
 }
@@ -0,0 +1,189 @@
+//! Module that serializes record batches to Markdown or HTML tables, to be pasted in tickets
+//! and wikis.
+//! Tables are built in memory and are meant to hold a limited number of rows
+
+use std::fmt::Write;
+
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::{DataType, SchemaRef};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::error::Result;
+
+use crate::common::TableMarkup;
+
+const ELLIPSIS: char = '…';
+
+/// Serializes record batches to a table written in a [`TableMarkup`] language
+pub(crate) struct MarkupSerializer {
+    markup: TableMarkup,
+
+    /// Maximum number of characters of a cell
+    max_cell_width: Option<usize>,
+}
+
+/// Escape a Markdown cell so that it does not break the table
+fn escape_markdown(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Numeric columns are aligned to the right
+fn is_right_aligned(data_type: &DataType) -> bool {
+    data_type.is_numeric()
+}
+
+impl MarkupSerializer {
+    pub(crate) fn new(markup: TableMarkup, max_cell_width: Option<usize>) -> Self {
+        Self {
+            markup,
+            max_cell_width,
+        }
+    }
+
+    fn truncate(&self, value: String) -> String {
+        match self.max_cell_width {
+            Some(width) if value.chars().count() > width => {
+                let mut truncated = value
+                    .chars()
+                    .take(width.saturating_sub(1))
+                    .collect::<String>();
+                truncated.push(ELLIPSIS);
+                truncated
+            }
+            _ => value,
+        }
+    }
+
+    /// Return the truncated textual value of every cell of `batches`, row by row
+    fn rows(&self, batches: &[RecordBatch]) -> Result<Vec<Vec<String>>> {
+        // Null values are written as empty cells
+        let options = FormatOptions::default();
+
+        let mut rows = Vec::new();
+        for batch in batches {
+            let formatters = batch
+                .columns()
+                .iter()
+                .map(|c| ArrayFormatter::try_new(c.as_ref(), &options))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for row in 0..batch.num_rows() {
+                rows.push(
+                    formatters
+                        .iter()
+                        .map(|f| self.truncate(f.value(row).to_string()))
+                        .collect(),
+                );
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Serialize `batches` of `schema` to a single table
+    pub(crate) fn serialize(&self, schema: &SchemaRef, batches: &[RecordBatch]) -> Result<String> {
+        let rows = self.rows(batches)?;
+        let headers = schema
+            .fields()
+            .iter()
+            .map(|f| {
+                (
+                    self.truncate(f.name().clone()),
+                    is_right_aligned(f.data_type()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut buffer = String::new();
+        // Writing to a `String` can not fail
+        let _ = match self.markup {
+            TableMarkup::Markdown => Self::write_markdown(&mut buffer, &headers, &rows),
+            TableMarkup::Html => Self::write_html(&mut buffer, &headers, &rows),
+        };
+
+        Ok(buffer)
+    }
+
+    fn write_markdown(
+        buffer: &mut String,
+        headers: &[(String, bool)],
+        rows: &[Vec<String>],
+    ) -> std::fmt::Result {
+        let header = headers
+            .iter()
+            .map(|(name, _)| escape_markdown(name))
+            .collect::<Vec<_>>();
+        writeln!(buffer, "| {} |", header.join(" | "))?;
+
+        let separator = headers
+            .iter()
+            .map(|(_, right)| if *right { "---:" } else { "---" })
+            .collect::<Vec<_>>();
+        writeln!(buffer, "| {} |", separator.join(" | "))?;
+
+        for row in rows {
+            let cells = row.iter().map(|c| escape_markdown(c)).collect::<Vec<_>>();
+            writeln!(buffer, "| {} |", cells.join(" | "))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_html(
+        buffer: &mut String,
+        headers: &[(String, bool)],
+        rows: &[Vec<String>],
+    ) -> std::fmt::Result {
+        let align = |right: bool| {
+            if right {
+                " style=\"text-align: right\""
+            } else {
+                ""
+            }
+        };
+
+        writeln!(buffer, "<table>")?;
+        writeln!(buffer, "  <thead>")?;
+        writeln!(buffer, "    <tr>")?;
+        for (name, right) in headers {
+            writeln!(
+                buffer,
+                "      <th{}>{}</th>",
+                align(*right),
+                escape_html(name)
+            )?;
+        }
+        writeln!(buffer, "    </tr>")?;
+        writeln!(buffer, "  </thead>")?;
+
+        writeln!(buffer, "  <tbody>")?;
+        for row in rows {
+            writeln!(buffer, "    <tr>")?;
+            for ((_, right), cell) in headers.iter().zip(row) {
+                writeln!(
+                    buffer,
+                    "      <td{}>{}</td>",
+                    align(*right),
+                    escape_html(cell)
+                )?;
+            }
+            writeln!(buffer, "    </tr>")?;
+        }
+        writeln!(buffer, "  </tbody>")?;
+        writeln!(buffer, "</table>")?;
+
+        Ok(())
+    }
+}
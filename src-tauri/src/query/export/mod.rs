@@ -2,26 +2,184 @@
 //! This module contains code that has been directly imported from DataFusion
 //! that we need to override default DataFusion implementation
 
-use std::sync::Arc;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
 
+use async_compression::tokio::write::{BzEncoder, GzipEncoder, XzEncoder, ZstdEncoder};
+use bytes::Bytes;
+use datafusion::common::extensions_options;
+use datafusion::common::parsers::CompressionTypeVariant;
+use datafusion::config::ConfigExtension;
 use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
 use datafusion::error::Result;
+use datafusion::execution::TaskContext;
 use object_store::{buffered::BufWriter, path::Path, ObjectStore};
+use rand::distributions::DistString;
 use tokio::io::AsyncWrite;
 
+pub(super) mod avro;
+pub(super) mod csv;
+pub(super) mod database;
 mod demux;
+pub(super) mod ipc;
 pub(super) mod json;
+pub(super) mod markup;
 mod orchestration;
+pub(super) mod parquet;
+mod sink;
+pub(super) mod sql;
+mod staging;
+pub(super) mod target;
+pub(super) mod xlsx;
 
-/// Returns an [`AsyncWrite`] which writes to the given object store location
+/// An [`AsyncWrite`] to an object store location that can be aborted, which discards the data
+/// written so far along with any pending multipart upload
+trait AbortableWrite: AsyncWrite + Send + Unpin {
+    /// The underlying object store writer
+    fn buf_writer(&mut self) -> &mut BufWriter;
+}
+
+impl AbortableWrite for BufWriter {
+    fn buf_writer(&mut self) -> &mut BufWriter {
+        self
+    }
+}
+
+macro_rules! impl_abortable_encoder {
+    ($($encoder:ident),*) => {
+        $(
+            impl AbortableWrite for $encoder<BufWriter> {
+                fn buf_writer(&mut self) -> &mut BufWriter {
+                    self.get_mut()
+                }
+            }
+        )*
+    };
+}
+
+impl_abortable_encoder!(GzipEncoder, BzEncoder, XzEncoder, ZstdEncoder);
+
+/// Returns an [`AbortableWrite`] which writes to the given object store location
 /// with the specified compression.
-/// We drop the `AbortableWrite` struct and the writer will not try to cleanup on failure.
-/// Users can configure automatic cleanup with their cloud provider.
+/// Writers must be aborted on failure, otherwise multipart uploads are left behind.
+/// We can not rely on [`FileCompressionType::convert_async_writer`] since it does not give
+/// access to the underlying [`BufWriter`] anymore.
 async fn create_writer(
     file_compression_type: FileCompressionType,
     location: &Path,
     object_store: Arc<dyn ObjectStore>,
-) -> Result<Box<dyn AsyncWrite + Send + Unpin>> {
+) -> Result<Box<dyn AbortableWrite>> {
     let buf_writer = BufWriter::new(object_store, location.clone());
-    file_compression_type.convert_async_writer(buf_writer)
+
+    Ok(match file_compression_type.get_variant() {
+        CompressionTypeVariant::GZIP => Box::new(GzipEncoder::new(buf_writer)),
+        CompressionTypeVariant::BZIP2 => Box::new(BzEncoder::new(buf_writer)),
+        CompressionTypeVariant::XZ => Box::new(XzEncoder::new(buf_writer)),
+        CompressionTypeVariant::ZSTD => Box::new(ZstdEncoder::new(buf_writer)),
+        CompressionTypeVariant::UNCOMPRESSED => Box::new(buf_writer),
+    })
+}
+
+/// Abort a writer, discarding any data that has been written so far
+async fn abort_writer(writer: &mut dyn AbortableWrite) -> Result<()> {
+    Ok(writer.buf_writer().abort().await?)
+}
+
+extensions_options! {
+    /// Session options that define the names of exported files.
+    /// Exported files are named with a random identifier when these options are not registered
+    /// in the session
+    pub(crate) struct FileNameOptions {
+        pub prefix: String, default = "part".to_string()
+        pub timestamp: bool, default = false
+        pub sequence_digits: usize, default = 5
+    }
+}
+
+impl ConfigExtension for FileNameOptions {
+    const PREFIX: &'static str = "lens_file_name";
+}
+
+/// Names of the files written by a single export
+enum FileNaming {
+    /// Files are named after a random identifier
+    WriteId(String),
+
+    /// Files are named after a [`FileNameOptions`] template
+    Template {
+        base: String,
+        sequence_digits: usize,
+    },
+}
+
+impl FileNaming {
+    fn new(context: &TaskContext) -> Self {
+        let options = context
+            .session_config()
+            .options()
+            .extensions
+            .get::<FileNameOptions>();
+
+        match options {
+            Some(options) => {
+                let base = if options.timestamp {
+                    let now = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+                    format!("{}_{now}", options.prefix)
+                } else {
+                    options.prefix.clone()
+                };
+
+                Self::Template {
+                    base,
+                    sequence_digits: options.sequence_digits,
+                }
+            }
+            None => Self::WriteId(
+                rand::distributions::Alphanumeric.sample_string(&mut rand::thread_rng(), 16),
+            ),
+        }
+    }
+
+    /// Name of a file with its extension, `sequence` being the index of the file when
+    /// multiple files are written in the same directory
+    fn file_name(&self, sequence: Option<usize>, file_extension: &str) -> String {
+        match (self, sequence) {
+            (Self::WriteId(write_id), Some(sequence)) => {
+                format!("{write_id}_{sequence}.{file_extension}")
+            }
+            (Self::WriteId(write_id), None) => format!("{write_id}.{file_extension}"),
+            (
+                Self::Template {
+                    base,
+                    sequence_digits,
+                },
+                Some(sequence),
+            ) => format!("{base}_{sequence:0sequence_digits$}.{file_extension}"),
+            (Self::Template { base, .. }, None) => format!("{base}.{file_extension}"),
+        }
+    }
+}
+
+/// A buffer shared between a synchronous writer and the serializer that drains it
+#[derive(Debug, Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    /// Take the bytes that have been written so far
+    fn take(&self) -> Bytes {
+        let mut buffer = self.0.lock().expect("lock poisoned");
+        Bytes::from(std::mem::take(&mut *buffer))
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut buffer = self.0.lock().expect("lock poisoned");
+        buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
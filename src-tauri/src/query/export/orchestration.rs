@@ -22,6 +22,7 @@
 use std::sync::Arc;
 
 use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::common::runtime::SpawnedTask;
 use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
 use datafusion::datasource::file_format::write::BatchSerializer;
@@ -34,16 +35,33 @@ use datafusion::common::{internal_datafusion_err, internal_err};
 
 use bytes::Bytes;
 use futures::join;
-use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::io::AsyncWriteExt;
 use tokio::sync::mpsc::{self, Receiver};
 use tokio::task::JoinSet;
 
-use super::create_writer;
 use super::demux::start_demuxer_task;
+use super::staging::Staging;
+use super::target::ReplacedObjects;
+use super::{abort_writer, create_writer, AbortableWrite};
 
-type WriterType = Box<dyn AsyncWrite + Send + Unpin>;
+type WriterType = Box<dyn AbortableWrite>;
 type SerializerType = Arc<dyn BatchSerializer>;
 
+/// A serializer for file types that can not be serialized independently for each
+/// [`RecordBatch`], like file formats that write a footer once all batches have been written.
+/// A new serializer is created for every output file.
+pub(crate) trait StatefulBatchSerializer: Send {
+    /// Serializes a [`RecordBatch`] and returns the bytes that are ready to be written
+    fn serialize(&mut self, batch: RecordBatch) -> Result<Bytes>;
+
+    /// Finishes the file and returns the remaining bytes to write
+    fn finish(&mut self) -> Result<Bytes>;
+}
+
+/// Creates a [`StatefulBatchSerializer`] from the schema of the batches of an output file
+pub(crate) type StatefulSerializerFactory =
+    Arc<dyn Fn(SchemaRef) -> Result<Box<dyn StatefulBatchSerializer>> + Send + Sync>;
+
 /// Result of calling [`serialize_rb_stream_to_object_store`]
 pub(crate) enum SerializedRecordBatchResult {
     Success {
@@ -213,9 +231,13 @@ pub(crate) async fn stateless_serialize_and_write_files(
 
     // Finalize or abort writers as appropriate
     for mut writer in finished_writers.into_iter() {
-        writer.shutdown()
+        if any_errors {
+            any_abort_errors |= abort_writer(writer.as_mut()).await.is_err();
+        } else {
+            writer.shutdown()
                     .await
                     .map_err(|_| internal_datafusion_err!("Error encountered while finalizing writes! Partial results may have been written to ObjectStore!"))?;
+        }
     }
 
     if any_errors {
@@ -241,7 +263,7 @@ pub(crate) async fn stateless_multipart_put(
     data: SendableRecordBatchStream,
     context: &Arc<TaskContext>,
     file_extension: String,
-    get_serializer: Box<dyn Fn() -> Arc<dyn BatchSerializer> + Send>,
+    get_serializer: Box<dyn Fn() -> Arc<dyn BatchSerializer> + Send + Sync>,
     config: &FileSinkConfig,
     compression: FileCompressionType,
 ) -> Result<u64> {
@@ -276,31 +298,166 @@ pub(crate) async fn stateless_multipart_put(
     let write_coordinator_task = SpawnedTask::spawn(async move {
         stateless_serialize_and_write_files(rx_file_bundle, tx_row_cnt).await
     });
-    while let Some((location, rb_stream)) = file_stream_rx.recv().await {
-        let serializer = get_serializer();
-        let writer = create_writer(compression, &location, object_store.clone()).await?;
-
-        tx_file_bundle
-            .send((rb_stream, serializer, writer))
-            .await
-            .map_err(|_| {
-                internal_datafusion_err!("Writer receive file bundle channel closed unexpectedly!")
-            })?;
+
+    // Files are written to a staging location and only committed once all of them succeeded
+    let replaced = context.session_config().get_extension::<ReplacedObjects>();
+    let mut staging = Staging::new(object_store.clone(), replaced);
+    let result = async {
+        while let Some((location, rb_stream)) = file_stream_rx.recv().await {
+            let serializer = get_serializer();
+            let writer =
+                create_writer(compression, &staging.stage(&location), object_store.clone()).await?;
+
+            tx_file_bundle
+                .send((rb_stream, serializer, writer))
+                .await
+                .map_err(|_| {
+                    internal_datafusion_err!(
+                        "Writer receive file bundle channel closed unexpectedly!"
+                    )
+                })?;
+        }
+        Ok::<_, DataFusionError>(())
     }
+    .await;
 
     // Signal to the write coordinator that no more files are coming
     drop(tx_file_bundle);
+    // Stop the demuxer if files could not be created
+    drop(file_stream_rx);
 
     let (r1, r2) = join!(
         write_coordinator_task.join_unwind(),
         demux_task.join_unwind()
     );
-    r1?;
-    r2?;
 
-    let total_count = rx_row_cnt.await.map_err(|_| {
-        internal_datafusion_err!("Did not receive row count from write coordinator")
+    let result =
+        result.and(r1).and(r2).and(rx_row_cnt.await.map_err(|_| {
+            internal_datafusion_err!("Did not receive row count from write coordinator")
+        }));
+
+    staging.finish(result).await
+}
+
+/// Serializes a single data stream with a [`StatefulBatchSerializer`] and writes it to an
+/// ObjectStore. The serializer is created when the first [RecordBatch] is received.
+async fn stateful_serialize_rb_stream_to_object_store(
+    mut data_rx: Receiver<RecordBatch>,
+    get_serializer: StatefulSerializerFactory,
+    mut writer: WriterType,
+) -> Result<usize> {
+    let mut serializer: Option<Box<dyn StatefulBatchSerializer>> = None;
+    let mut row_count = 0;
+
+    let result = async {
+        while let Some(batch) = data_rx.recv().await {
+            let serializer = match &mut serializer {
+                Some(serializer) => serializer,
+                None => serializer.insert(get_serializer(batch.schema())?),
+            };
+
+            row_count += batch.num_rows();
+            let bytes = serializer.serialize(batch)?;
+            writer.write_all(&bytes).await.map_err(|e| {
+                DataFusionError::Execution(format!("Error writing to object store: {e}"))
+            })?;
+        }
+
+        if let Some(mut serializer) = serializer {
+            let bytes = serializer.finish()?;
+            writer.write_all(&bytes).await.map_err(|e| {
+                DataFusionError::Execution(format!("Error writing to object store: {e}"))
+            })?;
+        }
+
+        Ok::<_, DataFusionError>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        // The error that caused the abort is more relevant than a failure to abort
+        let _ = abort_writer(writer.as_mut()).await;
+        return Err(e);
+    }
+
+    writer.shutdown().await.map_err(|_| {
+        internal_datafusion_err!("Error encountered while finalizing writes! Partial results may have been written to ObjectStore!")
     })?;
 
-    Ok(total_count)
+    Ok(row_count)
+}
+
+/// Orchestrates multipart put of a dynamic number of output files from a single input stream
+/// for file types that need to keep state across the [RecordBatch]s of a file.
+/// Each output file is serialized sequentially by its own [`StatefulBatchSerializer`], while
+/// output files are written concurrently.
+pub(crate) async fn stateful_multipart_put(
+    data: SendableRecordBatchStream,
+    context: &Arc<TaskContext>,
+    file_extension: String,
+    get_serializer: StatefulSerializerFactory,
+    config: &FileSinkConfig,
+    compression: FileCompressionType,
+) -> Result<u64> {
+    let object_store = context
+        .runtime_env()
+        .object_store(&config.object_store_url)?;
+
+    let base_output_path = &config.table_paths[0];
+    let part_cols = if !config.table_partition_cols.is_empty() {
+        Some(config.table_partition_cols.clone())
+    } else {
+        None
+    };
+
+    let (demux_task, mut file_stream_rx) = start_demuxer_task(
+        data,
+        context,
+        part_cols,
+        base_output_path.clone(),
+        file_extension,
+        config.keep_partition_by_columns,
+    );
+
+    // Files are written to a staging location and only committed once all of them succeeded
+    let replaced = context.session_config().get_extension::<ReplacedObjects>();
+    let mut staging = Staging::new(object_store.clone(), replaced);
+    let mut join_set = JoinSet::new();
+    let mut result = async {
+        while let Some((location, rb_stream)) = file_stream_rx.recv().await {
+            let writer =
+                create_writer(compression, &staging.stage(&location), object_store.clone()).await?;
+            let get_serializer = Arc::clone(&get_serializer);
+
+            join_set.spawn(async move {
+                stateful_serialize_rb_stream_to_object_store(rb_stream, get_serializer, writer)
+                    .await
+            });
+        }
+        Ok::<_, DataFusionError>(())
+    }
+    .await;
+
+    // Stop the demuxer if files could not be created
+    drop(file_stream_rx);
+
+    // Every file is awaited so that failed writers are aborted before rolling back
+    let mut row_count = 0;
+    while let Some(joined) = join_set.join_next().await {
+        let joined = joined
+            .map_err(|e| {
+                internal_datafusion_err!("Unexpected join error while serializing file {e}")
+            })
+            .and_then(|r| r);
+        match joined {
+            Ok(cnt) => row_count += cnt,
+            Err(e) => result = result.and(Err(e)),
+        }
+    }
+
+    let result = result
+        .and(demux_task.join_unwind().await)
+        .map(|_| row_count as u64);
+
+    staging.finish(result).await
 }
@@ -0,0 +1,77 @@
+//! Module that defines a format to export data in Parquet.
+//! We need to define our own format instead of using the default DataFusion Parquet format
+//! so that Parquet files are written through our orchestration, and thus staged and committed
+//! like the files of every other export format
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::common::file_options::parquet_writer::ParquetWriterOptions;
+use datafusion::config::TableParquetOptions;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::error::Result;
+use datafusion::parquet::arrow::ArrowWriter;
+use datafusion::parquet::file::properties::WriterProperties;
+
+use super::orchestration::StatefulBatchSerializer;
+use super::sink::{self, Serializer};
+use super::SharedBuffer;
+
+/// Define a struct for serializing record batches of a single file in Parquet
+struct ParquetSerializer {
+    /// The writer, until the file is finished
+    writer: Option<ArrowWriter<SharedBuffer>>,
+    buffer: SharedBuffer,
+}
+
+impl ParquetSerializer {
+    fn try_new(schema: SchemaRef, properties: WriterProperties) -> Result<Self> {
+        let buffer = SharedBuffer::default();
+        let writer = ArrowWriter::try_new(buffer.clone(), schema, Some(properties))?;
+
+        Ok(Self {
+            writer: Some(writer),
+            buffer,
+        })
+    }
+}
+
+impl StatefulBatchSerializer for ParquetSerializer {
+    fn serialize(&mut self, batch: RecordBatch) -> Result<Bytes> {
+        if let Some(writer) = &mut self.writer {
+            // Row groups are buffered by the writer and only flushed once full
+            writer.write(&batch)?;
+        }
+
+        Ok(self.buffer.take())
+    }
+
+    fn finish(&mut self) -> Result<Bytes> {
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+
+        Ok(self.buffer.take())
+    }
+}
+
+pub(crate) fn factory(options: TableParquetOptions) -> Result<Arc<dyn FileFormatFactory>> {
+    let properties = ParquetWriterOptions::try_from(&options)?
+        .writer_options()
+        .clone();
+    let get_serializer = move |schema: SchemaRef| {
+        ParquetSerializer::try_new(schema, properties.clone())
+            .map(|s| Box::new(s) as Box<dyn StatefulBatchSerializer>)
+    };
+
+    // Pages are compressed by the Parquet writer, files are never compressed as a whole
+    Ok(sink::factory(
+        "Parquet",
+        "parquet",
+        FileCompressionType::UNCOMPRESSED,
+        Serializer::Stateful(Arc::new(get_serializer)),
+    ))
+}
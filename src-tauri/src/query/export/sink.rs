@@ -0,0 +1,238 @@
+//! Module that defines the generic write-only format shared by our export formats.
+//! Formats only differ by how they serialize record batches, the format and the sink that
+//! write their files through our orchestration are thus defined once here
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::common::{not_impl_err, GetExt, Statistics};
+use datafusion::datasource::file_format::write::BatchSerializer;
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::datasource::file_format::{file_compression_type::FileCompressionType, FileFormat};
+use datafusion::datasource::physical_plan::{FileScanConfig, FileSinkConfig};
+use datafusion::error::Result;
+use datafusion::execution::context::SessionState;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::physical_expr::PhysicalSortRequirement;
+use datafusion::physical_plan::insert::{DataSink, DataSinkExec};
+use datafusion::physical_plan::metrics::MetricsSet;
+use datafusion::physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan, PhysicalExpr};
+use object_store::{ObjectMeta, ObjectStore};
+
+use super::orchestration::{
+    stateful_multipart_put, stateless_multipart_put, StatefulSerializerFactory,
+};
+
+/// How the files of an export format are serialized
+#[derive(Clone)]
+pub(super) enum Serializer {
+    /// Record batches are serialized independently of each other, and thus in parallel
+    Stateless(Arc<dyn BatchSerializer>),
+
+    /// Every file is serialized sequentially by its own
+    /// [`StatefulBatchSerializer`](super::orchestration::StatefulBatchSerializer), for formats
+    /// with a header or a footer
+    Stateful(StatefulSerializerFactory),
+}
+
+/// Definition of an export format, shared by its factory, its format and its sink
+#[derive(Clone)]
+struct FormatSpec {
+    /// Display name of the format
+    name: &'static str,
+    /// Extension of the files to write, without the compression extension
+    ext: &'static str,
+    /// Compression of the whole files
+    compression: FileCompressionType,
+    serializer: Serializer,
+}
+
+impl fmt::Debug for FormatSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatSpec")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// A file format that can only be written
+#[derive(Debug)]
+struct WriteOnlyFormat(FormatSpec);
+
+#[derive(Debug)]
+struct WriteOnlyFormatFactory(FormatSpec);
+
+impl GetExt for WriteOnlyFormatFactory {
+    fn get_ext(&self) -> String {
+        self.0.ext.to_string()
+    }
+}
+
+impl FileFormatFactory for WriteOnlyFormatFactory {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn create(
+        &self,
+        _state: &SessionState,
+        _format_options: &std::collections::HashMap<String, String>,
+    ) -> Result<Arc<dyn FileFormat>> {
+        Ok(self.default())
+    }
+
+    fn default(&self) -> Arc<dyn FileFormat> {
+        Arc::new(WriteOnlyFormat(self.0.clone()))
+    }
+}
+
+#[async_trait]
+impl FileFormat for WriteOnlyFormat {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn get_ext(&self) -> String {
+        self.0.ext.to_string()
+    }
+
+    fn get_ext_with_compression(
+        &self,
+        file_compression_type: &FileCompressionType,
+    ) -> Result<String> {
+        Ok(format!("{}{}", self.0.ext, file_compression_type.get_ext()))
+    }
+
+    async fn infer_schema(
+        &self,
+        _state: &SessionState,
+        _store: &Arc<dyn ObjectStore>,
+        _objects: &[ObjectMeta],
+    ) -> Result<SchemaRef> {
+        not_impl_err!("{} files can only be written", self.0.name)
+    }
+
+    async fn infer_stats(
+        &self,
+        _state: &SessionState,
+        _store: &Arc<dyn ObjectStore>,
+        _table_schema: SchemaRef,
+        _object: &ObjectMeta,
+    ) -> Result<Statistics> {
+        not_impl_err!("{} files can only be written", self.0.name)
+    }
+
+    async fn create_physical_plan(
+        &self,
+        _state: &SessionState,
+        _conf: FileScanConfig,
+        _filters: Option<&Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        not_impl_err!("{} files can only be written", self.0.name)
+    }
+
+    async fn create_writer_physical_plan(
+        &self,
+        input: Arc<dyn ExecutionPlan>,
+        _state: &SessionState,
+        conf: FileSinkConfig,
+        order_requirements: Option<Vec<PhysicalSortRequirement>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        if conf.overwrite {
+            return not_impl_err!("{} files can not be overwritten yet", self.0.name);
+        }
+
+        let schema = Arc::clone(conf.output_schema());
+        let sink = Arc::new(ExportSink {
+            config: conf,
+            format: self.0.clone(),
+        });
+
+        Ok(Arc::new(DataSinkExec::new(input, sink, schema, order_requirements)) as _)
+    }
+}
+
+/// Implements [`DataSink`] for writing the files of an export format.
+struct ExportSink {
+    /// Config options for writing data
+    config: FileSinkConfig,
+    format: FormatSpec,
+}
+
+impl fmt::Debug for ExportSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExportSink")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl DisplayAs for ExportSink {
+    fn fmt_as(&self, _t: DisplayFormatType, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DataSink for ExportSink {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        None
+    }
+
+    async fn write_all(
+        &self,
+        data: SendableRecordBatchStream,
+        context: &Arc<TaskContext>,
+    ) -> Result<u64> {
+        let format = &self.format;
+        let file_extension = format!("{}{}", format.ext, format.compression.get_ext());
+        match &format.serializer {
+            Serializer::Stateless(serializer) => {
+                let serializer = Arc::clone(serializer);
+                stateless_multipart_put(
+                    data,
+                    context,
+                    file_extension,
+                    Box::new(move || Arc::clone(&serializer)),
+                    &self.config,
+                    format.compression,
+                )
+                .await
+            }
+            Serializer::Stateful(get_serializer) => {
+                stateful_multipart_put(
+                    data,
+                    context,
+                    file_extension,
+                    Arc::clone(get_serializer),
+                    &self.config,
+                    format.compression,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Create a write-only format named `name` that writes files with the `ext` extension,
+/// compressed with `compression` and serialized by `serializer`
+pub(super) fn factory(
+    name: &'static str,
+    ext: &'static str,
+    compression: FileCompressionType,
+    serializer: Serializer,
+) -> Arc<dyn FileFormatFactory> {
+    Arc::new(WriteOnlyFormatFactory(FormatSpec {
+        name,
+        ext,
+        compression,
+        serializer,
+    }))
+}
@@ -0,0 +1,312 @@
+//! Module that defines a format to export data as a script of SQL `INSERT` statements.
+//! Every file is a standalone script for the chosen [`SqlDialect`], optionally starting with the
+//! `CREATE TABLE` statement of the table derived from the schema of the exported data
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, BooleanArray, RecordBatch};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Field, Float64Type};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::common::config_datafusion_err;
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
+use datafusion::datasource::file_format::write::BatchSerializer;
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::error::Result;
+
+use crate::common::{SqlDialect, SqlExportOptions};
+
+use super::sink::{self, Serializer};
+
+impl SqlDialect {
+    /// Quote an identifier, escaping the quote character
+    pub(super) fn quote_identifier(&self, ident: &str) -> String {
+        match self {
+            Self::Mysql => format!("`{}`", ident.replace('`', "``")),
+            Self::Postgres | Self::Sqlite => format!("\"{}\"", ident.replace('"', "\"\"")),
+        }
+    }
+
+    /// Quote a string literal, escaping special characters
+    fn quote_string(&self, value: &str) -> String {
+        match self {
+            // MySQL treats backslashes as escape characters in string literals by default
+            Self::Mysql => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''")),
+            Self::Postgres | Self::Sqlite => format!("'{}'", value.replace('\'', "''")),
+        }
+    }
+
+    fn boolean(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (Self::Sqlite, true) => "1",
+            (Self::Sqlite, false) => "0",
+            (_, true) => "TRUE",
+            (_, false) => "FALSE",
+        }
+    }
+
+    fn float(&self, value: f64) -> String {
+        if value.is_finite() {
+            return value.to_string();
+        }
+
+        match self {
+            Self::Postgres if value.is_nan() => "'NaN'".to_string(),
+            Self::Postgres if value > 0.0 => "'Infinity'".to_string(),
+            Self::Postgres => "'-Infinity'".to_string(),
+            // Neither MySQL nor SQLite support non-finite floating point values
+            Self::Mysql | Self::Sqlite => "NULL".to_string(),
+        }
+    }
+
+    fn binary(&self, value: &[u8]) -> String {
+        let hex = value
+            .iter()
+            .fold(String::with_capacity(value.len() * 2), |mut hex, b| {
+                let _ = write!(hex, "{b:02X}");
+                hex
+            });
+
+        match self {
+            Self::Postgres => format!("'\\x{hex}'"),
+            Self::Mysql | Self::Sqlite => format!("X'{hex}'"),
+        }
+    }
+
+    pub(super) fn format_options(&self) -> FormatOptions<'static> {
+        let timestamp_tz_format = match self {
+            Self::Postgres => "%Y-%m-%d %H:%M:%S%.f%:z",
+            // MySQL and SQLite do not support timezone offsets in their datetime literals
+            Self::Mysql | Self::Sqlite => "%Y-%m-%d %H:%M:%S%.f",
+        };
+
+        FormatOptions::default()
+            .with_date_format(Some("%Y-%m-%d"))
+            .with_datetime_format(Some("%Y-%m-%d %H:%M:%S"))
+            .with_timestamp_format(Some("%Y-%m-%d %H:%M:%S%.f"))
+            .with_timestamp_tz_format(Some(timestamp_tz_format))
+            .with_time_format(Some("%H:%M:%S%.f"))
+    }
+
+    /// Column type of the `CREATE TABLE` statement for a value of `data_type`
+    pub(super) fn column_type(&self, data_type: &DataType) -> String {
+        match self {
+            Self::Postgres => match data_type {
+                DataType::Boolean => "BOOLEAN".to_string(),
+                DataType::Int8 | DataType::Int16 | DataType::UInt8 => "SMALLINT".to_string(),
+                DataType::Int32 | DataType::UInt16 => "INTEGER".to_string(),
+                DataType::Int64 | DataType::UInt32 => "BIGINT".to_string(),
+                DataType::UInt64 => "NUMERIC(20)".to_string(),
+                DataType::Float16 | DataType::Float32 => "REAL".to_string(),
+                DataType::Float64 => "DOUBLE PRECISION".to_string(),
+                DataType::Decimal128(p, s) | DataType::Decimal256(p, s) => {
+                    format!("NUMERIC({p}, {s})")
+                }
+                DataType::Binary
+                | DataType::LargeBinary
+                | DataType::BinaryView
+                | DataType::FixedSizeBinary(_) => "BYTEA".to_string(),
+                DataType::Date32 | DataType::Date64 => "DATE".to_string(),
+                DataType::Time32(_) | DataType::Time64(_) => "TIME".to_string(),
+                DataType::Timestamp(_, None) => "TIMESTAMP".to_string(),
+                DataType::Timestamp(_, Some(_)) => "TIMESTAMPTZ".to_string(),
+                DataType::Dictionary(_, value_type) => self.column_type(value_type),
+                _ => "TEXT".to_string(),
+            },
+            Self::Mysql => match data_type {
+                DataType::Boolean => "BOOLEAN".to_string(),
+                DataType::Int8 => "TINYINT".to_string(),
+                DataType::Int16 => "SMALLINT".to_string(),
+                DataType::Int32 => "INT".to_string(),
+                DataType::Int64 => "BIGINT".to_string(),
+                DataType::UInt8 => "TINYINT UNSIGNED".to_string(),
+                DataType::UInt16 => "SMALLINT UNSIGNED".to_string(),
+                DataType::UInt32 => "INT UNSIGNED".to_string(),
+                DataType::UInt64 => "BIGINT UNSIGNED".to_string(),
+                DataType::Float16 | DataType::Float32 => "FLOAT".to_string(),
+                DataType::Float64 => "DOUBLE".to_string(),
+                DataType::Decimal128(p, s) | DataType::Decimal256(p, s) => {
+                    format!("DECIMAL({p}, {s})")
+                }
+                DataType::Binary
+                | DataType::LargeBinary
+                | DataType::BinaryView
+                | DataType::FixedSizeBinary(_) => "LONGBLOB".to_string(),
+                DataType::Date32 | DataType::Date64 => "DATE".to_string(),
+                DataType::Time32(_) | DataType::Time64(_) => "TIME(6)".to_string(),
+                DataType::Timestamp(_, _) => "DATETIME(6)".to_string(),
+                DataType::Dictionary(_, value_type) => self.column_type(value_type),
+                _ => "LONGTEXT".to_string(),
+            },
+            // SQLite only has a few storage classes
+            Self::Sqlite => match data_type {
+                data_type if data_type.is_integer() => "INTEGER".to_string(),
+                DataType::Boolean => "INTEGER".to_string(),
+                DataType::Float16 | DataType::Float32 | DataType::Float64 => "REAL".to_string(),
+                DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => "NUMERIC".to_string(),
+                DataType::Binary
+                | DataType::LargeBinary
+                | DataType::BinaryView
+                | DataType::FixedSizeBinary(_) => "BLOB".to_string(),
+                DataType::Dictionary(_, value_type) => self.column_type(value_type),
+                _ => "TEXT".to_string(),
+            },
+        }
+    }
+}
+
+/// A column of a [`RecordBatch`] converted to be written as SQL literals
+enum SqlColumn<'a> {
+    Boolean(&'a BooleanArray),
+
+    /// Integer and decimal values, written as is
+    Number(ArrayFormatter<'a>),
+
+    /// Floating point values, casted to `Float64`
+    Float(ArrayRef),
+
+    /// Binary values, casted to `Binary`
+    Binary(ArrayRef),
+
+    /// Any other value, written as a string literal of its textual representation
+    Text(ArrayFormatter<'a>),
+}
+
+impl<'a> SqlColumn<'a> {
+    fn try_new(array: &'a ArrayRef, options: &'a FormatOptions<'a>) -> Result<Self> {
+        Ok(match array.data_type() {
+            DataType::Boolean => Self::Boolean(array.as_boolean()),
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => {
+                Self::Float(cast(array, &DataType::Float64)?)
+            }
+            data_type if data_type.is_numeric() => {
+                Self::Number(ArrayFormatter::try_new(array.as_ref(), options)?)
+            }
+            DataType::Binary
+            | DataType::LargeBinary
+            | DataType::BinaryView
+            | DataType::FixedSizeBinary(_) => Self::Binary(cast(array, &DataType::Binary)?),
+            _ => Self::Text(ArrayFormatter::try_new(array.as_ref(), options)?),
+        })
+    }
+
+    fn literal(&self, array: &ArrayRef, idx: usize, dialect: SqlDialect) -> String {
+        if array.is_null(idx) {
+            return "NULL".to_string();
+        }
+
+        match self {
+            Self::Boolean(array) => dialect.boolean(array.value(idx)).to_string(),
+            Self::Number(formatter) => formatter.value(idx).to_string(),
+            Self::Float(array) => dialect.float(array.as_primitive::<Float64Type>().value(idx)),
+            Self::Binary(array) => dialect.binary(array.as_binary::<i32>().value(idx)),
+            Self::Text(formatter) => dialect.quote_string(&formatter.value(idx).to_string()),
+        }
+    }
+}
+
+/// Define a struct for serializing record batches to SQL statements
+struct SqlSerializer {
+    options: SqlExportOptions,
+}
+
+impl SqlSerializer {
+    fn create_table(&self, fields: &[&Field]) -> String {
+        let dialect = self.options.dialect;
+        let columns = fields
+            .iter()
+            .map(|field| {
+                let not_null = if field.is_nullable() { "" } else { " NOT NULL" };
+                format!(
+                    "  {} {}{not_null}",
+                    dialect.quote_identifier(field.name()),
+                    dialect.column_type(field.data_type())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (\n{columns}\n);\n\n",
+            dialect.quote_identifier(&self.options.table_name)
+        )
+    }
+}
+
+impl BatchSerializer for SqlSerializer {
+    fn serialize(&self, batch: RecordBatch, initial: bool) -> Result<Bytes> {
+        let dialect = self.options.dialect;
+        let schema = batch.schema();
+        let fields = schema
+            .fields()
+            .iter()
+            .map(|f| f.as_ref())
+            .collect::<Vec<_>>();
+
+        let mut buffer = String::new();
+        if initial && self.options.create_table {
+            buffer.push_str(&self.create_table(&fields));
+        }
+
+        let format_options = dialect.format_options();
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|array| Ok((array, SqlColumn::try_new(array, &format_options)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let insert_into = format!(
+            "INSERT INTO {} ({}) VALUES\n",
+            dialect.quote_identifier(&self.options.table_name),
+            fields
+                .iter()
+                .map(|f| dialect.quote_identifier(f.name()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let rows = (0..batch.num_rows()).collect::<Vec<_>>();
+        for chunk in rows.chunks(self.options.rows_per_statement) {
+            buffer.push_str(&insert_into);
+            for (i, &idx) in chunk.iter().enumerate() {
+                let values = columns
+                    .iter()
+                    .map(|(array, column)| column.literal(array, idx, dialect))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let separator = if i + 1 == chunk.len() { ";" } else { "," };
+                // Writing to a `String` can not fail
+                let _ = writeln!(buffer, "  ({values}){separator}");
+            }
+        }
+
+        Ok(Bytes::from(buffer))
+    }
+}
+
+pub(crate) fn factory(
+    options: SqlExportOptions,
+    compression: FileCompressionType,
+) -> Result<Arc<dyn FileFormatFactory>> {
+    if options.rows_per_statement == 0 {
+        return Err(config_datafusion_err!(
+            "The number of rows per SQL statement must be greater than 0"
+        ));
+    }
+
+    if options.table_name.is_empty() {
+        return Err(config_datafusion_err!(
+            "The SQL table name can not be empty"
+        ));
+    }
+
+    let serializer = SqlSerializer { options };
+    Ok(sink::factory(
+        "SQL",
+        "sql",
+        compression,
+        Serializer::Stateless(Arc::new(serializer)),
+    ))
+}
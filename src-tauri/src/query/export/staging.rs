@@ -0,0 +1,199 @@
+//! Module that makes exports atomic.
+//! Files of an export are first written next to their final location under a hidden staging
+//! name, and are only renamed to their final name once every file of the export has been
+//! successfully written. When an export fails, staged files are deleted instead.
+//! Objects replaced by an export in overwrite mode are only deleted once the export has been
+//! committed, so that a failed export leaves existing data untouched
+
+use std::sync::Arc;
+
+use datafusion::error::{DataFusionError, Result};
+use futures::{StreamExt, TryStreamExt};
+use object_store::{path::Path, ObjectStore};
+use rand::distributions::DistString;
+
+use super::target::ReplacedObjects;
+
+/// Tracks the files that have been staged by a single export
+pub(super) struct Staging {
+    /// Identifier of the export, used to name staged files
+    id: String,
+    object_store: Arc<dyn ObjectStore>,
+    /// Staged and final locations of the files of the export
+    files: Vec<(Path, Path)>,
+    /// Existing objects to delete once the export has been committed
+    replaced: Vec<Path>,
+}
+
+impl Staging {
+    pub(super) fn new(
+        object_store: Arc<dyn ObjectStore>,
+        replaced: Option<Arc<ReplacedObjects>>,
+    ) -> Self {
+        Self {
+            id: rand::distributions::Alphanumeric.sample_string(&mut rand::thread_rng(), 16),
+            object_store,
+            files: Vec::new(),
+            replaced: replaced.map(|r| r.0.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Register a file to write at `location` and return the location to write it to instead
+    pub(super) fn stage(&mut self, location: &Path) -> Path {
+        let mut parts = location.parts().collect::<Vec<_>>();
+        let file_name = parts
+            .pop()
+            .map(|part| part.as_ref().to_string())
+            .unwrap_or_default();
+
+        let staged = Path::from_iter(parts).child(format!(".staging-{}-{file_name}", self.id));
+        self.files.push((staged.clone(), location.clone()));
+        staged
+    }
+
+    /// Commit the export if `result` is a success, otherwise roll it back
+    pub(super) async fn finish<T>(self, result: Result<T>) -> Result<T> {
+        match result {
+            Ok(value) => {
+                self.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.rollback(0).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Move every staged file to its final location, then delete the objects it replaces.
+    /// When a file can not be moved, the files that have already been moved are deleted along
+    /// with the staged files. Existing objects that were overwritten by a moved file with the
+    /// same name can not be restored
+    async fn commit(self) -> Result<()> {
+        for (idx, (staged, location)) in self.files.iter().enumerate() {
+            if let Err(e) = self.object_store.rename(staged, location).await {
+                self.rollback(idx).await;
+                return Err(DataFusionError::Execution(format!(
+                    "Error committing exported file {location}, the export has been rolled back \
+                     and the {idx} files already committed have been deleted: {e}"
+                )));
+            }
+        }
+
+        // Objects overwritten by a file of the export are already replaced
+        let replaced = self
+            .replaced
+            .iter()
+            .filter(|path| !self.files.iter().any(|(_, location)| location == *path))
+            .cloned()
+            .map(Ok)
+            .collect::<Vec<_>>();
+        if replaced.is_empty() {
+            return Ok(());
+        }
+
+        self.object_store
+            .delete_stream(futures::stream::iter(replaced).boxed())
+            .try_collect::<Vec<_>>()
+            .await
+            .map_err(|e| {
+                DataFusionError::Execution(format!(
+                    "The export has been committed but some of the objects it replaces could \
+                     not be deleted: {e}"
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    /// Delete the files of the export, the first `committed` files from their final location
+    /// and the other ones from their staging location
+    async fn rollback(&self, committed: usize) {
+        for (idx, (staged, location)) in self.files.iter().enumerate() {
+            let path = if idx < committed { location } else { staged };
+            // Files that have not been written or whose upload has been aborted do not exist.
+            // Cleaning up is best effort, the error that caused the rollback is what matters
+            let _ = self.object_store.delete(path).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::{memory::InMemory, PutPayload};
+
+    use super::*;
+
+    async fn put(store: &Arc<dyn ObjectStore>, location: &Path, data: &'static [u8]) {
+        store
+            .put(location, PutPayload::from_static(data))
+            .await
+            .unwrap();
+    }
+
+    async fn locations(store: &Arc<dyn ObjectStore>) -> Vec<String> {
+        let mut locations = store
+            .list(None)
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        locations.sort();
+        locations
+    }
+
+    #[test]
+    fn stages_next_to_the_final_location() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let mut staging = Staging::new(store, None);
+
+        let staged = staging.stage(&Path::from("out/year=2024/part-0.csv"));
+        let mut parts = staged.parts().map(|p| p.as_ref().to_string());
+        assert_eq!(parts.next().as_deref(), Some("out"));
+        assert_eq!(parts.next().as_deref(), Some("year=2024"));
+        let file_name = parts.next().unwrap();
+        assert!(file_name.starts_with(".staging-"));
+        assert!(file_name.ends_with("-part-0.csv"));
+        assert!(parts.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn commit_moves_files_and_deletes_replaced_objects() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        put(&store, &Path::from("out/old-0.csv"), b"old").await;
+        put(&store, &Path::from("out/part-0.csv"), b"old").await;
+
+        let replaced = ReplacedObjects(vec![
+            Path::from("out/old-0.csv"),
+            Path::from("out/part-0.csv"),
+        ]);
+        let mut staging = Staging::new(Arc::clone(&store), Some(Arc::new(replaced)));
+        let location = Path::from("out/part-0.csv");
+        let staged = staging.stage(&location);
+        put(&store, &staged, b"new").await;
+
+        assert_eq!(staging.finish(Ok(1)).await.unwrap(), 1);
+        assert_eq!(locations(&store).await, vec!["out/part-0.csv"]);
+        let data = store.get(&location).await.unwrap().bytes().await.unwrap();
+        assert_eq!(data.as_ref(), b"new");
+    }
+
+    #[tokio::test]
+    async fn rollback_deletes_staged_files_and_keeps_existing_objects() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        put(&store, &Path::from("out/old-0.csv"), b"old").await;
+
+        let replaced = ReplacedObjects(vec![Path::from("out/old-0.csv")]);
+        let mut staging = Staging::new(Arc::clone(&store), Some(Arc::new(replaced)));
+        let staged = staging.stage(&Path::from("out/part-0.csv"));
+        put(&store, &staged, b"new").await;
+        // Files that have not been written yet are skipped
+        staging.stage(&Path::from("out/part-1.csv"));
+
+        let result = staging
+            .finish::<()>(Err(DataFusionError::Execution("failed".to_string())))
+            .await;
+        assert!(result.is_err());
+        assert_eq!(locations(&store).await, vec!["out/old-0.csv"]);
+    }
+}
@@ -0,0 +1,281 @@
+//! Module that prepares the target location of an export according to its [`WriteMode`].
+//! Sinks never overwrite anything by themselves, existing data is thus checked here, the same
+//! way for local paths and object stores, and the objects to replace are only deleted once the
+//! export has been committed.
+//! Remote targets are also checked to be writable so that exports fail before any work is done
+
+use std::sync::Arc;
+
+use datafusion::common::not_impl_err;
+use datafusion::datasource::listing::ListingTableUrl;
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::SessionState;
+use futures::TryStreamExt;
+use object_store::{path::Path, ObjectStore, PutPayload};
+use rand::distributions::DistString;
+
+use crate::common::WriteMode;
+
+/// Existing objects of an export target that are replaced by an export in overwrite mode.
+/// They are given to the sinks through the session config and deleted once the export has
+/// been committed
+#[derive(Debug, Default)]
+pub(crate) struct ReplacedObjects(pub(crate) Vec<Path>);
+
+/// Return the object store registered for `url`
+fn object_store(state: &SessionState, url: &ListingTableUrl) -> Result<Arc<dyn ObjectStore>> {
+    state.runtime_env().object_store(url).map_err(|_| {
+        let url: &url::Url = url.as_ref();
+        DataFusionError::Configuration(format!(
+            "No datasource is registered for {}://{}, register a datasource with credentials \
+             for this location before exporting to it",
+            url.scheme(),
+            url.host_str().unwrap_or_default()
+        ))
+    })
+}
+
+/// Make sure that the credentials of `store` allow writing under `url` by writing and deleting
+/// an empty object
+async fn check_writable(store: &Arc<dyn ObjectStore>, url: &ListingTableUrl) -> Result<()> {
+    let name = format!(
+        ".write-check-{}",
+        rand::distributions::Alphanumeric.sample_string(&mut rand::thread_rng(), 16)
+    );
+    let mut parts = url.prefix().parts().collect::<Vec<_>>();
+    if !url.is_collection() {
+        parts.pop();
+    }
+    let location = Path::from_iter(parts).child(name);
+
+    let denied = |e: object_store::Error| {
+        DataFusionError::Execution(format!(
+            "Can not write to {url}, check the permissions of the datasource credentials: {e}"
+        ))
+    };
+    store
+        .put(&location, PutPayload::new())
+        .await
+        .map_err(denied)?;
+    store.delete(&location).await.map_err(denied)?;
+
+    Ok(())
+}
+
+/// Return the objects that would be replaced or mixed with the files of an export: the file
+/// itself for a single file export, or every object under the directory otherwise
+async fn existing_objects(
+    store: &Arc<dyn ObjectStore>,
+    url: &ListingTableUrl,
+    single_file: bool,
+) -> Result<Vec<Path>> {
+    if single_file {
+        return match store.head(url.prefix()).await {
+            Ok(meta) => Ok(vec![meta.location]),
+            Err(object_store::Error::NotFound { .. }) => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    Ok(store
+        .list(Some(url.prefix()))
+        .map_ok(|meta| meta.location)
+        .try_collect()
+        .await?)
+}
+
+/// Prepare the target `url` of an export before writing its files and return the objects that
+/// the export replaces.
+/// `single_file` tells whether the export writes a single file at `url` or files in the
+/// `url` directory. Overwriting a directory replaces everything under it, nested directories
+/// included
+pub(crate) async fn prepare(
+    state: &SessionState,
+    url: &ListingTableUrl,
+    single_file: bool,
+    mode: WriteMode,
+) -> Result<ReplacedObjects> {
+    let store = object_store(state, url)?;
+    if url.scheme() != "file" {
+        check_writable(&store, url).await?;
+    }
+
+    let existing = existing_objects(&store, url, single_file).await?;
+    if existing.is_empty() {
+        return Ok(ReplacedObjects::default());
+    }
+
+    match mode {
+        WriteMode::ErrorIfExists => Err(DataFusionError::Execution(format!(
+            "Export target {url} already exists, use the append or overwrite mode to write to it"
+        ))),
+        WriteMode::Append if single_file => {
+            not_impl_err!("Can not append to the existing file {url}")
+        }
+        WriteMode::Append => Ok(ReplacedObjects::default()),
+        WriteMode::Overwrite => Ok(ReplacedObjects(existing)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::SessionContext;
+    use object_store::memory::InMemory;
+    use url::Url;
+
+    use super::*;
+
+    /// Session whose `memory://bucket` locations are stored in the returned store
+    fn session() -> (SessionState, Arc<dyn ObjectStore>) {
+        let ctx = SessionContext::new();
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        ctx.register_object_store(&Url::parse("memory://bucket").unwrap(), Arc::clone(&store));
+        (ctx.state(), store)
+    }
+
+    async fn put(store: &Arc<dyn ObjectStore>, location: &str) {
+        store
+            .put(&Path::from(location), PutPayload::from_static(b"data"))
+            .await
+            .unwrap();
+    }
+
+    fn url(location: &str) -> ListingTableUrl {
+        ListingTableUrl::parse(location).unwrap()
+    }
+
+    #[tokio::test]
+    async fn empty_target_replaces_nothing() {
+        let (state, store) = session();
+        put(&store, "other/part-0.csv").await;
+
+        for mode in [
+            WriteMode::ErrorIfExists,
+            WriteMode::Append,
+            WriteMode::Overwrite,
+        ] {
+            let replaced = prepare(&state, &url("memory://bucket/out/"), false, mode)
+                .await
+                .unwrap();
+            assert!(replaced.0.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn existing_target_fails_in_error_mode() {
+        let (state, store) = session();
+        put(&store, "out/part-0.csv").await;
+
+        let err = prepare(
+            &state,
+            &url("memory://bucket/out/"),
+            false,
+            WriteMode::ErrorIfExists,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+
+        let err = prepare(
+            &state,
+            &url("memory://bucket/out/part-0.csv"),
+            true,
+            WriteMode::ErrorIfExists,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+    }
+
+    #[tokio::test]
+    async fn append_keeps_existing_objects() {
+        let (state, store) = session();
+        put(&store, "out/part-0.csv").await;
+
+        let replaced = prepare(
+            &state,
+            &url("memory://bucket/out/"),
+            false,
+            WriteMode::Append,
+        )
+        .await
+        .unwrap();
+        assert!(replaced.0.is_empty());
+
+        // A single file can not be appended to
+        let result = prepare(
+            &state,
+            &url("memory://bucket/out/part-0.csv"),
+            true,
+            WriteMode::Append,
+        )
+        .await;
+        assert!(matches!(result, Err(DataFusionError::NotImplemented(_))));
+    }
+
+    #[tokio::test]
+    async fn overwrite_replaces_the_whole_directory() {
+        let (state, store) = session();
+        put(&store, "out/part-0.csv").await;
+        put(&store, "out/year=2024/part-1.csv").await;
+        put(&store, "other/part-0.csv").await;
+
+        let mut replaced = prepare(
+            &state,
+            &url("memory://bucket/out/"),
+            false,
+            WriteMode::Overwrite,
+        )
+        .await
+        .unwrap()
+        .0;
+        replaced.sort();
+        assert_eq!(
+            replaced,
+            vec![
+                Path::from("out/part-0.csv"),
+                Path::from("out/year=2024/part-1.csv")
+            ]
+        );
+
+        let replaced = prepare(
+            &state,
+            &url("memory://bucket/out/part-0.csv"),
+            true,
+            WriteMode::Overwrite,
+        )
+        .await
+        .unwrap();
+        assert_eq!(replaced.0, vec![Path::from("out/part-0.csv")]);
+    }
+
+    #[tokio::test]
+    async fn write_check_leaves_no_object() {
+        let (state, store) = session();
+
+        prepare(
+            &state,
+            &url("memory://bucket/out/"),
+            false,
+            WriteMode::ErrorIfExists,
+        )
+        .await
+        .unwrap();
+        let objects = store.list(None).try_collect::<Vec<_>>().await.unwrap();
+        assert!(objects.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unregistered_target_fails() {
+        let (state, _) = session();
+
+        let result = prepare(
+            &state,
+            &url("memory://other/out/"),
+            false,
+            WriteMode::Overwrite,
+        )
+        .await;
+        assert!(matches!(result, Err(DataFusionError::Configuration(_))));
+    }
+}
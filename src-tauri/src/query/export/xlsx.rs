@@ -0,0 +1,488 @@
+//! Module that defines a format to export data in Excel (XLSX) workbooks.
+//! A workbook can not be written incrementally, the whole workbook is thus built in memory
+//! and written to a single file once the input stream has been consumed.
+//! When partition columns are provided, every distinct partition key is written to its own
+//! worksheet instead of its own hive-style directory
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use datafusion::arrow::array::{Array, ArrayRef, AsArray, BooleanArray};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Float64Type, Int64Type, SchemaRef, TimeUnit};
+use datafusion::arrow::util::display::{ArrayFormatter, FormatOptions};
+use datafusion::common::{not_impl_err, GetExt, Statistics};
+use datafusion::datasource::file_format::FileFormatFactory;
+use datafusion::datasource::file_format::{file_compression_type::FileCompressionType, FileFormat};
+use datafusion::datasource::physical_plan::{FileScanConfig, FileSinkConfig};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::execution::context::SessionState;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::physical_expr::PhysicalSortRequirement;
+use datafusion::physical_plan::insert::{DataSink, DataSinkExec};
+use datafusion::physical_plan::metrics::MetricsSet;
+use datafusion::physical_plan::{DisplayAs, DisplayFormatType, ExecutionPlan, PhysicalExpr};
+use futures::StreamExt;
+use object_store::{ObjectMeta, ObjectStore};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use crate::common::XlsxExportOptions;
+
+use super::staging::Staging;
+use super::target::ReplacedObjects;
+use super::FileNaming;
+
+/// Maximum length of a worksheet name allowed by Excel
+const SHEET_NAME_MAX_LEN: usize = 31;
+
+/// Characters that are not allowed by Excel in a worksheet name
+const SHEET_NAME_INVALID_CHARS: [char; 7] = ['[', ']', ':', '*', '?', '/', '\\'];
+
+/// Number of days between the Excel epoch (1899-12-30) and the Unix epoch
+const EXCEL_UNIX_EPOCH_DAYS: f64 = 25569.0;
+
+const MILLIS_PER_DAY: f64 = 86_400_000.0;
+
+fn xlsx_err(e: XlsxError) -> DataFusionError {
+    DataFusionError::External(Box::new(e))
+}
+
+#[derive(Debug)]
+struct XlsxFormat {
+    options: XlsxExportOptions,
+}
+
+#[derive(Debug)]
+struct XlsxFormatFactory {
+    options: XlsxExportOptions,
+}
+
+impl GetExt for XlsxFormatFactory {
+    fn get_ext(&self) -> String {
+        "xlsx".to_string()
+    }
+}
+
+impl FileFormatFactory for XlsxFormatFactory {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn create(
+        &self,
+        _state: &SessionState,
+        _format_options: &std::collections::HashMap<String, String>,
+    ) -> Result<Arc<dyn FileFormat>> {
+        Ok(self.default())
+    }
+
+    fn default(&self) -> Arc<dyn FileFormat> {
+        Arc::new(XlsxFormat {
+            options: self.options.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl FileFormat for XlsxFormat {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn get_ext(&self) -> String {
+        "xlsx".to_string()
+    }
+
+    fn get_ext_with_compression(
+        &self,
+        file_compression_type: &FileCompressionType,
+    ) -> Result<String> {
+        if file_compression_type.is_compressed() {
+            return not_impl_err!("XLSX files can not be compressed");
+        }
+
+        Ok(self.get_ext())
+    }
+
+    async fn infer_schema(
+        &self,
+        _state: &SessionState,
+        _store: &Arc<dyn ObjectStore>,
+        _objects: &[ObjectMeta],
+    ) -> Result<SchemaRef> {
+        not_impl_err!("XLSX files can only be written")
+    }
+
+    async fn infer_stats(
+        &self,
+        _state: &SessionState,
+        _store: &Arc<dyn ObjectStore>,
+        _table_schema: SchemaRef,
+        _object: &ObjectMeta,
+    ) -> Result<Statistics> {
+        not_impl_err!("XLSX files can only be written")
+    }
+
+    async fn create_physical_plan(
+        &self,
+        _state: &SessionState,
+        _conf: FileScanConfig,
+        _filters: Option<&Arc<dyn PhysicalExpr>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        not_impl_err!("XLSX files can only be written")
+    }
+
+    async fn create_writer_physical_plan(
+        &self,
+        input: Arc<dyn ExecutionPlan>,
+        _state: &SessionState,
+        conf: FileSinkConfig,
+        order_requirements: Option<Vec<PhysicalSortRequirement>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        if conf.overwrite {
+            return not_impl_err!("XLSX files can not be overwritten yet");
+        }
+
+        let schema = Arc::clone(conf.output_schema());
+        let sink = Arc::new(XlsxSink::new(conf, self.options.clone()));
+
+        Ok(Arc::new(DataSinkExec::new(input, sink, schema, order_requirements)) as _)
+    }
+}
+
+/// Formats of the cells of a workbook
+struct CellFormats {
+    header: Format,
+    date: Format,
+    datetime: Format,
+}
+
+/// A column of a [`RecordBatch`] converted to be written in typed cells
+enum XlsxColumn<'a> {
+    Boolean(&'a BooleanArray),
+
+    /// Numeric values, casted to `Float64`
+    Number(ArrayRef),
+
+    /// Dates, as milliseconds since the Unix epoch
+    Date(ArrayRef),
+
+    /// Timestamps in UTC, as milliseconds since the Unix epoch
+    DateTime(ArrayRef),
+
+    /// Any other value, written as its textual representation
+    Text(&'a ArrayRef, ArrayFormatter<'a>),
+}
+
+/// Cast temporal `array` to `data_type` and return its values in milliseconds since the Unix epoch
+fn to_millis(array: &ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    Ok(cast(&cast(array, data_type)?, &DataType::Int64)?)
+}
+
+/// Convert milliseconds since the Unix epoch to an Excel serial date
+fn to_excel_date(millis: i64) -> f64 {
+    millis as f64 / MILLIS_PER_DAY + EXCEL_UNIX_EPOCH_DAYS
+}
+
+impl<'a> XlsxColumn<'a> {
+    fn try_new(array: &'a ArrayRef, options: &'a FormatOptions<'a>) -> Result<Self> {
+        Ok(match array.data_type() {
+            DataType::Boolean => Self::Boolean(array.as_boolean()),
+            DataType::Date32 | DataType::Date64 => Self::Date(to_millis(array, &DataType::Date64)?),
+            DataType::Timestamp(_, tz) => Self::DateTime(to_millis(
+                array,
+                &DataType::Timestamp(TimeUnit::Millisecond, tz.clone()),
+            )?),
+            data_type if data_type.is_numeric() => Self::Number(cast(array, &DataType::Float64)?),
+            _ => Self::Text(array, ArrayFormatter::try_new(array.as_ref(), options)?),
+        })
+    }
+
+    fn write(
+        &self,
+        worksheet: &mut Worksheet,
+        row: u32,
+        col: u16,
+        idx: usize,
+        formats: &CellFormats,
+    ) -> Result<(), XlsxError> {
+        match self {
+            Self::Boolean(array) if array.is_valid(idx) => {
+                worksheet.write_boolean(row, col, array.value(idx))?;
+            }
+            Self::Number(array) if array.is_valid(idx) => {
+                let value = array.as_primitive::<Float64Type>().value(idx);
+                worksheet.write_number(row, col, value)?;
+            }
+            Self::Date(array) if array.is_valid(idx) => {
+                let value = to_excel_date(array.as_primitive::<Int64Type>().value(idx));
+                worksheet.write_number_with_format(row, col, value, &formats.date)?;
+            }
+            Self::DateTime(array) if array.is_valid(idx) => {
+                let value = to_excel_date(array.as_primitive::<Int64Type>().value(idx));
+                worksheet.write_number_with_format(row, col, value, &formats.datetime)?;
+            }
+            Self::Text(array, formatter) if array.is_valid(idx) => {
+                worksheet.write_string(row, col, formatter.value(idx).to_string())?;
+            }
+            // Null values are written as empty cells
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// A worksheet of the workbook being written
+struct Sheet {
+    /// Index of the worksheet in the workbook
+    index: usize,
+
+    /// Next row to write
+    row: u32,
+}
+
+/// Create a valid and unique worksheet name from a partition key
+fn sheet_name(
+    part_key: &[String],
+    partition_by: &[(String, DataType)],
+    existing: &HashSet<String>,
+) -> String {
+    let name = partition_by
+        .iter()
+        .zip(part_key)
+        .map(|((col, _), value)| format!("{col}={value}"))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    // Excel does not allow a worksheet name to start or end with an apostrophe
+    let name = name
+        .replace(&SHEET_NAME_INVALID_CHARS[..], "_")
+        .trim_matches('\'')
+        .to_string();
+    let name = if name.is_empty() {
+        "Sheet".to_string()
+    } else {
+        name
+    };
+
+    // Worksheet names are case-insensitive
+    let is_unique = |name: &str| !existing.contains(&name.to_lowercase());
+
+    let truncated = name.chars().take(SHEET_NAME_MAX_LEN).collect::<String>();
+    if is_unique(&truncated) {
+        return truncated;
+    }
+
+    (2..)
+        .map(|n| {
+            let suffix = format!(" ({n})");
+            let base = name
+                .chars()
+                .take(SHEET_NAME_MAX_LEN - suffix.len())
+                .collect::<String>();
+            format!("{base}{suffix}")
+        })
+        .find(|name| is_unique(name))
+        .expect("a unique worksheet name should exist")
+}
+
+/// Implements [`DataSink`] for writing to an XLSX file.
+struct XlsxSink {
+    /// Config options for writing data
+    config: FileSinkConfig,
+    /// Options of the workbook
+    options: XlsxExportOptions,
+}
+
+impl std::fmt::Debug for XlsxSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("XlsxSink").finish()
+    }
+}
+
+impl DisplayAs for XlsxSink {
+    fn fmt_as(&self, _t: DisplayFormatType, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl XlsxSink {
+    /// Create from config.
+    fn new(config: FileSinkConfig, options: XlsxExportOptions) -> Self {
+        Self { config, options }
+    }
+
+    /// Build the whole workbook from the input stream and return its bytes along with the
+    /// number of rows that have been written
+    async fn build_workbook(&self, mut data: SendableRecordBatchStream) -> Result<(Vec<u8>, u64)> {
+        let partition_by = &self.config.table_partition_cols;
+        let keep_partition_by_columns =
+            partition_by.is_empty() || self.config.keep_partition_by_columns;
+
+        let formats = CellFormats {
+            header: Format::new().set_bold(),
+            date: Format::new().set_num_format(&self.options.date_format),
+            datetime: Format::new().set_num_format(&self.options.datetime_format),
+        };
+        let format_options = FormatOptions::default();
+
+        let mut workbook = Workbook::new();
+        let mut sheets: HashMap<Vec<String>, Sheet> = HashMap::new();
+        let mut sheet_names = HashSet::new();
+        let mut row_count = 0;
+
+        while let Some(batch) = data.next().await.transpose()? {
+            let schema = batch.schema();
+
+            // Columns to write in the worksheets along with the name of their header
+            let columns = schema
+                .fields()
+                .iter()
+                .zip(batch.columns())
+                .filter(|(field, _)| {
+                    keep_partition_by_columns
+                        || !partition_by.iter().any(|(col, _)| col == field.name())
+                })
+                .map(|(field, array)| {
+                    Ok((field.name(), XlsxColumn::try_new(array, &format_options)?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let partition_columns =
+                partition_by
+                    .iter()
+                    .map(|(col, _)| {
+                        let array = batch.column_by_name(col).ok_or(DataFusionError::Execution(
+                            format!("PartitionBy Column {col} does not exist in source data!"),
+                        ))?;
+                        Ok((
+                            array,
+                            ArrayFormatter::try_new(array.as_ref(), &format_options)?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+            for idx in 0..batch.num_rows() {
+                let part_key = partition_columns
+                    .iter()
+                    .map(|(array, formatter)| {
+                        if array.is_valid(idx) {
+                            formatter.value(idx).to_string()
+                        } else {
+                            "null".to_string()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                if !sheets.contains_key(&part_key) {
+                    let worksheet = workbook.add_worksheet();
+                    if partition_by.is_empty() {
+                        if let Some(name) = &self.options.sheet_name {
+                            worksheet.set_name(name).map_err(xlsx_err)?;
+                        }
+                    } else {
+                        let name = sheet_name(&part_key, partition_by, &sheet_names);
+                        worksheet.set_name(&name).map_err(xlsx_err)?;
+                        sheet_names.insert(name.to_lowercase());
+                    }
+
+                    for (col, (name, _)) in columns.iter().enumerate() {
+                        let col = u16::try_from(col).map_err(|_| {
+                            DataFusionError::Execution(
+                                "Too many columns to export in XLSX".to_string(),
+                            )
+                        })?;
+                        worksheet
+                            .write_string_with_format(0, col, name.as_str(), &formats.header)
+                            .map_err(xlsx_err)?;
+                    }
+                    worksheet.set_freeze_panes(1, 0).map_err(xlsx_err)?;
+
+                    let index = sheets.len();
+                    sheets.insert(part_key.clone(), Sheet { index, row: 1 });
+                }
+
+                let sheet = sheets.get_mut(&part_key).ok_or(DataFusionError::Internal(
+                    "Key must exist since it was just inserted!".into(),
+                ))?;
+                let worksheet = workbook
+                    .worksheet_from_index(sheet.index)
+                    .map_err(xlsx_err)?;
+
+                for (col, (_, column)) in columns.iter().enumerate() {
+                    // The number of columns has been checked when writing the header
+                    column
+                        .write(worksheet, sheet.row, col as u16, idx, &formats)
+                        .map_err(xlsx_err)?;
+                }
+
+                sheet.row += 1;
+                row_count += 1;
+            }
+        }
+
+        // Excel can not open a workbook without any worksheet
+        if sheets.is_empty() {
+            workbook.add_worksheet();
+        }
+
+        let buffer = workbook.save_to_buffer().map_err(xlsx_err)?;
+        Ok((buffer, row_count))
+    }
+}
+
+#[async_trait]
+impl DataSink for XlsxSink {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        None
+    }
+
+    async fn write_all(
+        &self,
+        data: SendableRecordBatchStream,
+        context: &Arc<TaskContext>,
+    ) -> Result<u64> {
+        let object_store = context
+            .runtime_env()
+            .object_store(&self.config.object_store_url)?;
+
+        let base_output_path = &self.config.table_paths[0];
+        let location = if base_output_path.is_collection() {
+            let file_naming = FileNaming::new(context);
+            base_output_path
+                .prefix()
+                .child(file_naming.file_name(None, "xlsx"))
+        } else {
+            base_output_path.prefix().to_owned()
+        };
+
+        // The workbook is written to a staging location and only committed once it is complete,
+        // the objects it replaces are then deleted
+        let replaced = context.session_config().get_extension::<ReplacedObjects>();
+        let mut staging = Staging::new(object_store.clone(), replaced);
+        let staged = staging.stage(&location);
+        let result = async {
+            let (buffer, row_count) = self.build_workbook(data).await?;
+            object_store
+                .put(&staged, Bytes::from(buffer).into())
+                .await?;
+            Ok(row_count)
+        }
+        .await;
+
+        staging.finish(result).await
+    }
+}
+
+pub(crate) fn factory(options: XlsxExportOptions) -> Arc<dyn FileFormatFactory> {
+    Arc::new(XlsxFormatFactory { options })
+}
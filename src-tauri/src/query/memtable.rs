@@ -65,6 +65,39 @@ impl MemTable {
         let batches = self.shared.batches.read().expect("lock poisoned");
         batches.iter().map(|batch| batch.num_rows()).sum()
     }
+
+    /// Return a snapshot of the batches that have been inserted so far
+    pub fn batches(&self) -> Vec<RecordBatch> {
+        let batches = self.shared.batches.read().expect("lock poisoned");
+        batches.clone()
+    }
+
+    /// Return the rows that have been inserted so far, starting at `offset` and up to `limit`
+    /// rows if specified
+    pub fn slice(&self, offset: usize, limit: Option<usize>) -> Vec<RecordBatch> {
+        let batches = self.shared.batches.read().expect("lock poisoned");
+
+        let mut skip = offset;
+        let mut remaining = limit.unwrap_or(usize::MAX);
+        let mut slices = Vec::new();
+        for batch in batches.iter() {
+            if remaining == 0 {
+                break;
+            }
+
+            if skip >= batch.num_rows() {
+                skip -= batch.num_rows();
+                continue;
+            }
+
+            let len = (batch.num_rows() - skip).min(remaining);
+            slices.push(batch.slice(skip, len));
+            remaining -= len;
+            skip = 0;
+        }
+
+        slices
+    }
 }
 
 #[async_trait]
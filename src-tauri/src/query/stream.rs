@@ -4,15 +4,20 @@ use std::{collections::HashMap, sync::Arc};
 
 use datafusion::{
     arrow::{
-        array::AsArray,
-        datatypes::{SchemaRef, UInt64Type},
+        array::{AsArray, RecordBatch},
+        datatypes::{DataType, SchemaRef, UInt64Type},
         error::ArrowError,
-        util::display::{ArrayFormatter, FormatOptions},
     },
-    config::CsvOptions,
-    datasource::{file_format::format_as_file_type, provider_as_source},
+    common::{config_datafusion_err, Column},
+    datasource::{
+        file_format::{format_as_file_type, FileFormatFactory},
+        listing::ListingTableUrl,
+        provider_as_source, TableProvider,
+    },
     error::DataFusionError,
-    execution::{context::SessionState, SendableRecordBatchStream},
+    execution::{
+        context::SessionState, session_state::SessionStateBuilder, SendableRecordBatchStream,
+    },
     logical_expr::LogicalPlanBuilder,
     prelude::*,
     sql::TableReference,
@@ -22,11 +27,25 @@ use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::{
-    common::{self, ExportFormat, ExportOptions, StreamId},
+    common::{
+        self, CopyOptions, DatabaseExportOptions, ExportFormat, ExportOptions, StreamId,
+        WriteOptions,
+    },
     lens::LensResult,
+    table_provider::HIVE_DEFAULT_PARTITION,
 };
 
-use super::memtable::MemTable;
+use super::{
+    export::{
+        avro, csv,
+        database::{self, DatabaseKind},
+        ipc,
+        json::{self, JsonLayout},
+        markup::MarkupSerializer,
+        parquet, sql, target, xlsx, FileNameOptions,
+    },
+    memtable::MemTable,
+};
 
 /// Error that can occur during query stream manipulation
 #[derive(Debug, Error)]
@@ -67,11 +86,21 @@ pub enum QueryStreamRequest {
     Export {
         id: StreamId,
 
-        options: ExportOptions,
+        /// Boxed as the options of every export format make it much larger than other requests
+        options: Box<ExportOptions>,
 
         resp_tx: oneshot::Sender<LensResult<usize>>,
     },
 
+    /// Copy rows fetched by a given [`StreamId`] as a table described by [`CopyOptions`]
+    Copy {
+        id: StreamId,
+
+        options: CopyOptions,
+
+        resp_tx: oneshot::Sender<LensResult<String>>,
+    },
+
     /// Close and remove from memory any data fetched by a given [`StreamId`]
     Close {
         id: StreamId,
@@ -110,6 +139,21 @@ impl QueryStreamRequest {
         let (resp_tx, resp_rx) = oneshot::channel();
         (
             Self::Export {
+                id,
+                options: Box::new(options),
+                resp_tx,
+            },
+            resp_rx,
+        )
+    }
+
+    pub fn copy(
+        id: StreamId,
+        options: CopyOptions,
+    ) -> (Self, oneshot::Receiver<LensResult<String>>) {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        (
+            Self::Copy {
                 id,
                 options,
                 resp_tx,
@@ -215,7 +259,15 @@ impl QueryStreamer {
                 options,
                 resp_tx,
             } => {
-                let _ = resp_tx.send(self.export(id, options).await.map_err(Into::into));
+                let _ = resp_tx.send(self.export(id, *options).await.map_err(Into::into));
+            }
+
+            QueryStreamRequest::Copy {
+                id,
+                options,
+                resp_tx,
+            } => {
+                let _ = resp_tx.send(self.copy(id, options).map_err(Into::into));
             }
 
             QueryStreamRequest::Close { id, resp_tx } => {
@@ -255,69 +307,80 @@ impl QueryStreamer {
 
         entry.table.insert(batch.clone());
 
-        let schema = batch.schema();
-        let columns = schema
-            .fields()
-            .iter()
-            .map(|f| f.name())
-            .cloned()
-            .collect::<Vec<_>>();
-
-        let options = FormatOptions::default().with_display_error(true);
-        let formatters = batch
-            .columns()
-            .iter()
-            .map(|c| ArrayFormatter::try_new(c.as_ref(), &options))
-            .collect::<Result<Vec<_>, _>>()?;
-
-        let rows = (0..batch.num_rows())
-            .map(|row| common::Row {
-                columns: columns.clone(),
-                values: formatters
-                    .iter()
-                    .map(|f| f.value(row).to_string())
-                    .collect(),
-            })
-            .collect();
-
+        let rows = common::Row::from_batch(&batch)?;
         Ok(Some(rows))
     }
 
-    async fn export(&mut self, id: StreamId, options: ExportOptions) -> StreamResult<usize> {
+    async fn export(&mut self, id: StreamId, mut options: ExportOptions) -> StreamResult<usize> {
         let Some(entry) = self.streams.get(&id) else {
             return Err(StreamError::UnknownStream(id));
         };
 
-        let df = entry.scan(self.ctx.state())?;
+        let df = entry.scan(self.export_state(&options.write_options)?)?;
+        let df = Self::partition(df, &mut options.write_options)?;
+        let compression = options.compression.into();
 
         let batches = match options.format {
-            ExportFormat::Csv => {
-                let csv_options = CsvOptions::default().with_has_header(true);
-                let write_options = options.write_options.into();
-                df.write_csv(&options.path, write_options, Some(csv_options))
+            ExportFormat::Csv(csv_options) => {
+                let format = csv::factory(csv_options, compression)?;
+                self.copy_to(df, format, options.path, options.write_options)
+                    .await?
+            }
+            ExportFormat::Parquet(parquet_options) => {
+                let table_options = self.ctx.state().default_table_options().parquet;
+                let format = parquet::factory(parquet_options.into_table_options(table_options))?;
+                self.copy_to(df, format, options.path, options.write_options)
                     .await?
             }
-            ExportFormat::Parquet => {
-                df.write_parquet(&options.path, options.write_options.into(), None)
+            ExportFormat::Json(json_options) => {
+                let format = json::factory(JsonLayout::Array, json_options, compression);
+                self.copy_to(df, format, options.path, options.write_options)
                     .await?
             }
-            ExportFormat::Json => {
-                // NOTE(oktal): we do not directly call `DataFrame::write_json` because we can not
-                // use our own serialization format.
-                // Instead, we create a logicial plan with our own json serialization format
-                let format = super::export::json::factory();
-                let file_type = format_as_file_type(format);
-
-                let plan = LogicalPlanBuilder::copy_to(
-                    df.into_unoptimized_plan(),
-                    options.path.clone(),
-                    file_type,
-                    Default::default(),
-                    options.write_options.partition_by,
-                )?
-                .build()?;
-
-                DataFrame::new(self.ctx.state(), plan).collect().await?
+            ExportFormat::Ndjson(json_options) => {
+                let format = json::factory(JsonLayout::NewlineDelimited, json_options, compression);
+                self.copy_to(df, format, options.path, options.write_options)
+                    .await?
+            }
+            ExportFormat::ArrowIpc(ipc_options) => {
+                let format = ipc::factory(ipc_options);
+                self.copy_to(df, format, options.path, options.write_options)
+                    .await?
+            }
+            ExportFormat::Xlsx(xlsx_options) => {
+                let format = xlsx::factory(xlsx_options);
+                self.copy_to(df, format, options.path, options.write_options)
+                    .await?
+            }
+            ExportFormat::Avro(avro_options) => {
+                let format = avro::factory(avro_options);
+                self.copy_to(df, format, options.path, options.write_options)
+                    .await?
+            }
+            ExportFormat::Sql(sql_options) => {
+                let format = sql::factory(sql_options, compression)?;
+                self.copy_to(df, format, options.path, options.write_options)
+                    .await?
+            }
+            ExportFormat::Sqlite(db_options) => {
+                return Self::write_database(
+                    entry,
+                    DatabaseKind::Sqlite,
+                    db_options,
+                    options.path,
+                    options.write_options,
+                )
+                .await;
+            }
+            ExportFormat::Duckdb(db_options) => {
+                return Self::write_database(
+                    entry,
+                    DatabaseKind::Duckdb,
+                    db_options,
+                    options.path,
+                    options.write_options,
+                )
+                .await;
             }
         };
 
@@ -331,6 +394,204 @@ impl QueryStreamer {
         Ok(count.unwrap_or(0) as usize)
     }
 
+    fn copy(&self, id: StreamId, options: CopyOptions) -> StreamResult<String> {
+        let Some(entry) = self.streams.get(&id) else {
+            return Err(StreamError::UnknownStream(id));
+        };
+
+        let batches = entry.table.slice(options.offset, options.limit);
+        let serializer = MarkupSerializer::new(options.markup, options.max_cell_width);
+        let table = serializer.serialize(&entry.table.schema(), &batches)?;
+
+        Ok(table)
+    }
+
+    /// Create the session state of an export, configured according to its [`WriteOptions`]
+    fn export_state(&self, write_options: &WriteOptions) -> StreamResult<SessionState> {
+        let mut config = self.ctx.copied_config();
+        let options = config.options_mut();
+
+        if let Some(max_rows) = write_options.max_rows_per_file {
+            if max_rows == 0 {
+                return Err(config_datafusion_err!(
+                    "The maximum number of rows per file must be greater than 0"
+                )
+                .into());
+            }
+            options.execution.soft_max_rows_per_output_file = max_rows;
+        }
+
+        if let Some(max_batches) = write_options.max_buffered_batches {
+            // Each file buffers half of the batches in its channel and the other half while
+            // serializing them
+            if max_batches < 2 {
+                return Err(config_datafusion_err!(
+                    "The maximum number of buffered batches must be at least 2"
+                )
+                .into());
+            }
+            options.execution.max_buffered_batches_per_output_file = max_batches;
+        }
+
+        if let Some(parallel_files) = write_options.minimum_parallel_files {
+            if parallel_files == 0 {
+                return Err(config_datafusion_err!(
+                    "The minimum number of parallel files must be greater than 0"
+                )
+                .into());
+            }
+            options.execution.minimum_parallel_output_files = parallel_files;
+        }
+
+        if write_options.single_file {
+            if write_options.max_rows_per_file.is_some()
+                || write_options.minimum_parallel_files.is_some()
+            {
+                return Err(config_datafusion_err!(
+                    "A single file export can not limit the rows per file or write files in parallel"
+                )
+                .into());
+            }
+            options.execution.soft_max_rows_per_output_file = usize::MAX;
+            options.execution.minimum_parallel_output_files = 1;
+        }
+
+        if let Some(template) = &write_options.file_name {
+            options.extensions.insert(FileNameOptions {
+                prefix: template.prefix.clone(),
+                timestamp: template.timestamp,
+                sequence_digits: template.sequence_digits,
+            });
+        }
+
+        Ok(SessionStateBuilder::new_from_existing(self.ctx.state())
+            .with_config(config)
+            .build())
+    }
+
+    /// Evaluate the partitions of an export as columns of `df` and replace the partitions of
+    /// `write_options` by these columns.
+    /// Partition values are written in directory names, they are thus evaluated as strings so that
+    /// partitions can be of any type
+    fn partition(df: DataFrame, write_options: &mut WriteOptions) -> StreamResult<DataFrame> {
+        if write_options.partition_by.is_empty() && write_options.partition_exprs.is_empty() {
+            return Ok(df);
+        }
+
+        // Column names are not normalized, unlike identifiers of SQL expressions
+        let mut partitions = write_options
+            .partition_by
+            .iter()
+            .map(|name| (name.clone(), col(Column::from_name(name))))
+            .collect::<Vec<_>>();
+
+        for partition in write_options.partition_exprs.drain(..) {
+            if partition.name.is_empty() {
+                return Err(config_datafusion_err!(
+                    "The partition {} must be named",
+                    partition.expr
+                )
+                .into());
+            }
+            if df
+                .schema()
+                .has_column_with_unqualified_name(&partition.name)
+            {
+                return Err(config_datafusion_err!(
+                    "The partition {} conflicts with a column of the same name",
+                    partition.name
+                )
+                .into());
+            }
+
+            let expr = df.parse_sql_expr(&partition.expr)?;
+            partitions.push((partition.name, expr));
+        }
+
+        let names = partitions
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        let mut exprs = df
+            .schema()
+            .fields()
+            .iter()
+            .filter(|f| !names.contains(f.name()))
+            .map(|f| col(Column::from_name(f.name())))
+            .collect::<Vec<_>>();
+        exprs.extend(partitions.into_iter().map(|(name, expr)| {
+            coalesce(vec![
+                cast(expr, DataType::Utf8),
+                lit(HIVE_DEFAULT_PARTITION),
+            ])
+            .alias(name)
+        }));
+
+        write_options.partition_by = names;
+        Ok(df.select(exprs)?)
+    }
+
+    /// Export the rows fetched by a stream into a table of a database file
+    async fn write_database(
+        entry: &StreamEntry,
+        kind: DatabaseKind,
+        options: DatabaseExportOptions,
+        path: String,
+        write_options: WriteOptions,
+    ) -> StreamResult<usize> {
+        if !write_options.partition_by.is_empty() {
+            return Err(DataFusionError::NotImplemented(
+                "Database exports can not be partitioned".to_string(),
+            )
+            .into());
+        }
+
+        let count = database::write(
+            kind,
+            &path,
+            options,
+            entry.table.schema(),
+            entry.table.batches(),
+            write_options.mode,
+        )
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Export a [`DataFrame`] with one of our own file formats
+    async fn copy_to(
+        &self,
+        df: DataFrame,
+        format: Arc<dyn FileFormatFactory>,
+        path: String,
+        write_options: WriteOptions,
+    ) -> StreamResult<Vec<RecordBatch>> {
+        // NOTE(oktal): we do not directly call `DataFrame::write_csv` or `DataFrame::write_json`
+        // because we can not use our own serialization format.
+        // Instead, we create a logicial plan with our own serialization format
+        let file_type = format_as_file_type(format);
+
+        // The session state of the data frame holds the configuration of the export
+        let (mut state, plan) = df.into_parts();
+
+        let url = ListingTableUrl::parse(&path)?;
+        let single_file = !url.is_collection() && write_options.partition_by.is_empty();
+        let replaced = target::prepare(&state, &url, single_file, write_options.mode).await?;
+        state.config_mut().set_extension(Arc::new(replaced));
+        let plan = LogicalPlanBuilder::copy_to(
+            plan,
+            path,
+            file_type,
+            Default::default(),
+            write_options.partition_by,
+        )?
+        .build()?;
+
+        Ok(DataFrame::new(state, plan).collect().await?)
+    }
+
     fn close(&mut self, id: StreamId) -> StreamResult<()> {
         self.streams
             .remove(&id)
@@ -1,15 +1,389 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use datafusion::arrow::datatypes::DataType;
+use chrono::NaiveDate;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use datafusion::catalog::{Session, TableProviderFactory};
-use datafusion::common::config_datafusion_err;
+use datafusion::common::{config_datafusion_err, extensions_options, ScalarValue, Statistics};
+use datafusion::config::{ConfigExtension, ExtensionOptions};
 use datafusion::datasource::listing::{
     ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
 };
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
 use datafusion::{datasource::TableProvider, logical_expr::CreateExternalTable};
+use futures::TryStreamExt;
+use object_store::{ObjectMeta, ObjectStore};
+
+use crate::common::{
+    ColumnStatistics, ColumnTypeChange, FileSchemaDiff, PartitionStatistics, TableStatistics,
+};
+use crate::filtered_store;
+
+/// Value of the partition of null values, as named by Hive
+pub(crate) const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+extensions_options! {
+    /// Table options that control how the schema of a table is inferred, set in the `OPTIONS`
+    /// of `CREATE EXTERNAL TABLE` with the `lens.` prefix
+    pub(crate) struct SchemaInferenceOptions {
+        /// Infer the schema from the files of the table, otherwise the declared columns are used
+        /// as-is
+        pub infer_schema: bool, default = true
+
+        /// Number of files to infer the schema from, all files are read if 0
+        pub sample_files: usize, default = 0
+
+        /// Number of records read from each file to infer its schema, for CSV and JSON tables.
+        /// The default of the file format is used if 0
+        pub sample_records: usize, default = 0
+
+        /// Strategy used to merge the schemas of the files of the table, either `default` or
+        /// `union_by_name`, which CSV tables do not support
+        pub schema_merge: String, default = "default".to_string()
+
+        /// Discover partition columns from `key=value` directories when none are declared.
+        /// Directories named after a column of the files are not partitions
+        pub discover_partitions: bool, default = false
+
+        /// Comma separated glob patterns of the files to read, relative to each location.
+        /// All files are read if not set
+        pub include: String, default = String::new()
+
+        /// Comma separated glob patterns of the files to skip, relative to each location
+        pub exclude: String, default = String::new()
+
+        /// JSON array of the urls of additional locations of the table, read along with its
+        /// `LOCATION`, e.g. `["s3://bucket/2023/", "s3://archive/2022/"]`
+        pub locations: String, default = String::new()
+    }
+}
+
+impl ConfigExtension for SchemaInferenceOptions {
+    const PREFIX: &'static str = "lens";
+}
+
+/// Strategy used to merge the schemas inferred from the files of a table
+enum SchemaMerge {
+    /// Schemas are inferred and merged by the file format
+    Default,
+
+    /// Schemas are inferred file by file and merged by column name. Columns that are missing in
+    /// some files are nullable and conflicting types are safely widened, unsafe conflicts fail
+    UnionByName,
+}
+
+impl SchemaInferenceOptions {
+    /// Extract the schema inference options from the options of a table and return the remaining
+    /// options, which belong to the file format
+    fn from_table_options(
+        options: &HashMap<String, String>,
+    ) -> Result<(Self, HashMap<String, String>)> {
+        let mut inference = Self::default();
+        let mut remaining = HashMap::new();
+
+        for (key, value) in options {
+            match key
+                .strip_prefix(Self::PREFIX)
+                .and_then(|k| k.strip_prefix('.'))
+            {
+                Some(key) => inference.set(key, value)?,
+                None => {
+                    remaining.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        Ok((inference, remaining))
+    }
+
+    /// Locations of a table, its `location` followed by its additional locations
+    fn table_locations(&self, location: &str) -> Result<Vec<String>> {
+        let mut locations = vec![location.to_string()];
+        if !self.locations.is_empty() {
+            let additional: Vec<String> = serde_json::from_str(&self.locations).map_err(|e| {
+                config_datafusion_err!(
+                    "Invalid locations {}, expected a JSON array of urls: {e}",
+                    self.locations
+                )
+            })?;
+            locations.extend(additional);
+        }
+
+        Ok(locations)
+    }
+
+    fn merge(&self) -> Result<SchemaMerge> {
+        match self.schema_merge.to_lowercase().as_str() {
+            "default" => Ok(SchemaMerge::Default),
+            "union_by_name" => Ok(SchemaMerge::UnionByName),
+            other => Err(config_datafusion_err!(
+                "Unknown schema merge strategy {other}, expected default or union_by_name"
+            )),
+        }
+    }
+}
+
+/// Return the type that can hold every value of both `left` and `right` without loss, if any
+fn widen(left: &DataType, right: &DataType) -> Option<DataType> {
+    let width = |t: &DataType| t.primitive_width().unwrap_or_default();
+    let wider = |l: &DataType, r: &DataType| {
+        if width(l) >= width(r) {
+            l.clone()
+        } else {
+            r.clone()
+        }
+    };
+
+    match (left, right) {
+        (l, r) if l == r => Some(l.clone()),
+        (DataType::Null, other) | (other, DataType::Null) => Some(other.clone()),
+        (l, r) if l.is_signed_integer() && r.is_signed_integer() => Some(wider(l, r)),
+        (l, r) if l.is_unsigned_integer() && r.is_unsigned_integer() => Some(wider(l, r)),
+        // Unsigned integers fit in signed integers that are strictly wider
+        (s, u) | (u, s) if s.is_signed_integer() && u.is_unsigned_integer() => match width(u) {
+            1 => Some(wider(s, &DataType::Int16)),
+            2 => Some(wider(s, &DataType::Int32)),
+            4 => Some(DataType::Int64),
+            _ => None,
+        },
+        (l, r) if l.is_floating() && r.is_floating() => Some(wider(l, r)),
+        // Integers of up to 32 bits are exactly represented by doubles
+        (i, f) | (f, i) if i.is_integer() && f.is_floating() && width(i) <= 4 => {
+            Some(DataType::Float64)
+        }
+        (
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View,
+        ) => Some(DataType::LargeUtf8),
+        (
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView,
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView,
+        ) => Some(DataType::LargeBinary),
+        _ => None,
+    }
+}
+
+/// Merge the `schemas` of files by column name, in the order in which columns are first seen.
+/// Files are read with DataFusion's default schema adapter, which maps columns by name,
+/// fills missing columns with nulls and casts widened columns
+fn union_by_name(schemas: Vec<(String, SchemaRef)>) -> Result<Schema> {
+    let mut fields: Vec<Field> = Vec::new();
+    let num_schemas = schemas.len();
+    let mut occurrences: Vec<usize> = Vec::new();
+
+    for (file, schema) in schemas {
+        for field in schema.fields() {
+            match fields.iter().position(|f| f.name() == field.name()) {
+                Some(idx) => {
+                    let merged = &fields[idx];
+                    let data_type = widen(merged.data_type(), field.data_type()).ok_or(
+                        config_datafusion_err!(
+                            "Column {} of {file} is {}, which can not be safely merged with {}",
+                            field.name(),
+                            field.data_type(),
+                            merged.data_type()
+                        ),
+                    )?;
+
+                    fields[idx] = Field::new(
+                        merged.name(),
+                        data_type,
+                        merged.is_nullable() || field.is_nullable(),
+                    );
+                    occurrences[idx] += 1;
+                }
+                None => {
+                    fields.push(field.as_ref().clone());
+                    occurrences.push(1);
+                }
+            }
+        }
+    }
+
+    // Columns that are missing from some files are read as nulls
+    let fields = fields
+        .into_iter()
+        .zip(occurrences)
+        .map(|(field, count)| {
+            let nullable = field.is_nullable() || count < num_schemas;
+            field.with_nullable(nullable)
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Schema::new(fields))
+}
+
+/// List the files of a table, sorted by location
+async fn list_files(
+    state: &SessionState,
+    store: &dyn ObjectStore,
+    options: &ListingOptions,
+    table_path: &ListingTableUrl,
+) -> Result<Vec<ObjectMeta>> {
+    let mut files = table_path
+        .list_all_files(state, store, &options.file_extension)
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    files.sort_by(|a, b| a.location.cmp(&b.location));
+    Ok(files)
+}
+
+/// Infer the type of a partition column from its `values`: integers, dates or strings
+fn infer_partition_type(values: &BTreeSet<String>) -> DataType {
+    // Null partitions can only be parsed as strings
+    if values.contains(HIVE_DEFAULT_PARTITION) {
+        return default_partition_type();
+    }
+
+    if values.iter().all(|v| v.parse::<i64>().is_ok()) {
+        DataType::Int64
+    } else if values
+        .iter()
+        .all(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").is_ok())
+    {
+        DataType::Date32
+    } else {
+        default_partition_type()
+    }
+}
+
+/// This is the type that is returned by the default implementation of DataFusion when no schema
+/// is provided for partition columns.
+fn default_partition_type() -> DataType {
+    DataType::Dictionary(Box::new(DataType::UInt16), Box::new(DataType::Utf8))
+}
+
+/// Files of each location of a table, along with the store of the location
+type Listings = Vec<(Arc<dyn ObjectStore>, Vec<ObjectMeta>)>;
+
+/// List the files of every location of a table
+async fn list_table_files(
+    state: &SessionState,
+    options: &ListingOptions,
+    table_paths: &[ListingTableUrl],
+) -> Result<Listings> {
+    let mut listings = Vec::with_capacity(table_paths.len());
+    for table_path in table_paths {
+        let store = state.runtime_env().object_store(table_path)?;
+        let files = list_files(state, store.as_ref(), options, table_path).await?;
+        listings.push((store, files));
+    }
+
+    Ok(listings)
+}
+
+/// Discover the partition columns of a table from the `key=value` directories of its files.
+/// Only the leading partitions that are shared by every file are kept
+fn discover_partitions(
+    table_paths: &[ListingTableUrl],
+    listings: &Listings,
+) -> Vec<(String, DataType)> {
+    let files = table_paths
+        .iter()
+        .zip(listings)
+        .flat_map(|(table_path, (_, files))| files.iter().map(move |file| (table_path, file)));
+
+    let mut columns: Option<Vec<(String, BTreeSet<String>)>> = None;
+    for (table_path, file) in files {
+        let Some(parts) = file.location.prefix_match(table_path.prefix()) else {
+            continue;
+        };
+
+        // The last part is the name of the file
+        let mut parts = parts.collect::<Vec<_>>();
+        parts.pop();
+        let partitions = parts
+            .iter()
+            .map_while(|part| part.as_ref().split_once('='))
+            .collect::<Vec<_>>();
+
+        match &mut columns {
+            None => {
+                columns = Some(
+                    partitions
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), BTreeSet::from([value.to_string()])))
+                        .collect(),
+                )
+            }
+            Some(columns) => {
+                let shared = columns
+                    .iter()
+                    .zip(&partitions)
+                    .take_while(|((name, _), (key, _))| name == key)
+                    .count();
+                columns.truncate(shared);
+
+                for ((_, values), (_, value)) in columns.iter_mut().zip(&partitions) {
+                    values.insert(value.to_string());
+                }
+            }
+        }
+    }
+
+    columns
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, values)| {
+            let data_type = infer_partition_type(&values);
+            (name, data_type)
+        })
+        .collect()
+}
+
+/// Infer the schema of the files of a table according to `inference`
+async fn infer_schema(
+    state: &SessionState,
+    options: &ListingOptions,
+    listings: &Listings,
+    inference: &SchemaInferenceOptions,
+) -> Result<SchemaRef> {
+    // Sample files deterministically, in the order of the locations
+    let mut remaining = match inference.sample_files {
+        0 => usize::MAX,
+        sample_files => sample_files,
+    };
+    let listings = listings
+        .iter()
+        .map(|(store, files)| {
+            let files = &files[..files.len().min(remaining)];
+            remaining -= files.len();
+            (store, files)
+        })
+        .collect::<Vec<_>>();
+
+    match inference.merge()? {
+        // Files of different locations may live in different stores, each location is thus
+        // inferred on its own
+        SchemaMerge::Default => {
+            let mut schemas = Vec::with_capacity(listings.len());
+            for (store, files) in &listings {
+                let schema = options.format.infer_schema(state, store, files).await?;
+                schemas.push(schema.as_ref().clone());
+            }
+
+            Ok(Arc::new(Schema::try_merge(schemas)?))
+        }
+        SchemaMerge::UnionByName => {
+            let mut schemas = Vec::new();
+            for (store, files) in &listings {
+                for file in files.iter() {
+                    let schema = options
+                        .format
+                        .infer_schema(state, store, std::slice::from_ref(file))
+                        .await?;
+                    schemas.push((file.location.to_string(), schema));
+                }
+            }
+
+            Ok(Arc::new(union_by_name(schemas)?))
+        }
+    }
+}
 
 struct LensTableProvider;
 
@@ -20,18 +394,44 @@ impl TableProviderFactory for LensTableProvider {
         session: &dyn Session,
         cmd: &CreateExternalTable,
     ) -> Result<Arc<dyn TableProvider>> {
-        // Retrieve file format for the type of table to create
         let state = session
             .as_any()
             .downcast_ref::<SessionState>()
             .expect("`Session` should be a `SessionState`");
+
+        // Schema inference options are ours, the remaining options belong to the file format
+        let (inference, mut format_options) =
+            SchemaInferenceOptions::from_table_options(&cmd.options)?;
+        if inference.sample_records > 0 {
+            let file_type = cmd.file_type.to_uppercase();
+            if file_type != "CSV" && file_type != "JSON" {
+                return Err(config_datafusion_err!(
+                    "Sampling records is only supported by CSV and JSON tables"
+                ));
+            }
+            format_options.insert(
+                "format.schema_infer_max_rec".to_string(),
+                inference.sample_records.to_string(),
+            );
+        }
+
+        // CSV files are read by position, columns of files can thus not be mapped by name
+        if matches!(inference.merge()?, SchemaMerge::UnionByName)
+            && cmd.file_type.eq_ignore_ascii_case("CSV")
+        {
+            return Err(config_datafusion_err!(
+                "CSV columns are read by position and can not be merged by name"
+            ));
+        }
+
+        // Retrieve file format for the type of table to create
         let file_format = state
             .get_file_format_factory(cmd.file_type.as_str())
             .ok_or(config_datafusion_err!(
                 "Unable to create table with format {}! Could not find FileFormat.",
                 cmd.file_type
             ))?
-            .create(state, &cmd.options)?;
+            .create(state, &format_options)?;
 
         // Determine partition columns and their associated type if provided in the columns
         // definition
@@ -47,30 +447,82 @@ impl TableProviderFactory for LensTableProvider {
                 {
                     field.data_type().clone()
                 } else {
-                    // This is the type that is returned by the default implementation of DataFusion
-                    // when no schema is provided for partition columns.
-                    DataType::Dictionary(Box::new(DataType::UInt16), Box::new(DataType::Utf8))
+                    default_partition_type()
                 };
 
                 (p.clone(), data_type)
             })
-            .collect();
+            .collect::<Vec<_>>();
 
-        // Setup table
-        let table_path = ListingTableUrl::parse(&cmd.location)?;
+        // Setup table, a table can span additional locations
+        if cmd.location.is_empty() {
+            return Err(config_datafusion_err!("A table must have a location"));
+        }
+        let table_paths = inference
+            .table_locations(&cmd.location)?
+            .iter()
+            .map(|location| {
+                let table_path = ListingTableUrl::parse(location)?;
+                filtered_store::register(state, table_path, &inference.include, &inference.exclude)
+            })
+            .collect::<Result<Vec<_>>>()?;
         let options = ListingOptions::new(file_format)
             .with_collect_stat(state.config().collect_statistics())
             .with_target_partitions(state.config().target_partitions())
-            .with_table_partition_cols(partition_cols)
             .with_file_sort_order(cmd.order_exprs.clone());
 
-        options.validate_partitions(state, &table_path).await?;
+        // Files are listed once, to both discover partitions and infer the schema
+        let discover = partition_cols.is_empty() && inference.discover_partitions;
+        let listings = if discover || inference.infer_schema {
+            list_table_files(state, &options, &table_paths).await?
+        } else {
+            Vec::new()
+        };
+
+        let schema = if inference.infer_schema {
+            infer_schema(state, &options, &listings, &inference).await?
+        } else {
+            // Partition columns are not part of the files and are added back by the table
+            if cmd.schema.fields().is_empty() {
+                return Err(config_datafusion_err!(
+                    "Columns must be declared when the schema is not inferred"
+                ));
+            }
+            let is_partition = |name: &str| {
+                cmd.table_partition_cols
+                    .iter()
+                    .any(|p| p.eq_ignore_ascii_case(name))
+            };
+            let fields = cmd
+                .schema
+                .fields()
+                .iter()
+                .filter(|f| !is_partition(f.name()))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            Arc::new(Schema::new(fields))
+        };
+
+        // Partitions are discovered from the layout of the files when none are declared.
+        // Partitions are positional, discovery thus stops at the first directory that is named
+        // after a column of the files
+        let partition_cols = if discover {
+            discover_partitions(&table_paths, &listings)
+                .into_iter()
+                .take_while(|(name, _)| schema.field_with_name(name).is_err())
+                .collect()
+        } else {
+            partition_cols
+        };
+        let options = options.with_table_partition_cols(partition_cols);
 
-        // Infer schema
-        let schema = options.infer_schema(state, &table_path).await?;
+        for table_path in &table_paths {
+            options.validate_partitions(state, table_path).await?;
+        }
 
         // Create table
-        let config = ListingTableConfig::new(table_path)
+        let config = ListingTableConfig::new_with_multi_paths(table_paths)
             .with_listing_options(options)
             .with_schema(schema);
         let provider = ListingTable::try_new(config)?
@@ -83,6 +535,553 @@ impl TableProviderFactory for LensTableProvider {
     }
 }
 
+/// Identifiers of the filtered stores that the files of the table defined by `cmd` are read
+/// through, if any
+pub(crate) fn filtered_stores(cmd: &CreateExternalTable) -> Result<Vec<String>> {
+    let (inference, _) = SchemaInferenceOptions::from_table_options(&cmd.options)?;
+    if inference.include.is_empty() && inference.exclude.is_empty() {
+        return Ok(vec![]);
+    }
+
+    inference
+        .table_locations(&cmd.location)?
+        .iter()
+        .map(|location| {
+            let table_path = ListingTableUrl::parse(location)?;
+            Ok(filtered_store::store_id(
+                &table_path,
+                &inference.include,
+                &inference.exclude,
+            ))
+        })
+        .collect()
+}
+
+/// Return the name of the partition columns of a table, if it is a listing table
+pub(crate) fn partition_columns(table: &dyn TableProvider) -> Vec<String> {
+    table
+        .as_any()
+        .downcast_ref::<ListingTable>()
+        .map(|listing| {
+            listing
+                .options()
+                .table_partition_cols
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Compare the schema of every file of a listing table with the schema of the table and return
+/// the differences of the files that do not match it
+pub(crate) async fn schema_diff(
+    state: &SessionState,
+    table: &ListingTable,
+) -> Result<Vec<FileSchemaDiff>> {
+    let options = table.options();
+    let table_schema = table.schema();
+
+    // Partition columns are not part of the files
+    let is_partition = |name: &str| options.table_partition_cols.iter().any(|(p, _)| p == name);
+
+    let mut diffs = Vec::new();
+    for table_path in table.table_paths() {
+        let store = state.runtime_env().object_store(table_path)?;
+        for file in list_files(state, store.as_ref(), options, table_path).await? {
+            let file_schema = options
+                .format
+                .infer_schema(state, &store, std::slice::from_ref(&file))
+                .await?;
+
+            let missing_columns = table_schema
+                .fields()
+                .iter()
+                .filter(|f| !is_partition(f.name()))
+                .filter(|f| file_schema.field_with_name(f.name()).is_err())
+                .map(|f| f.name().clone())
+                .collect::<Vec<_>>();
+
+            let mut extra_columns = Vec::new();
+            let mut changed_columns = Vec::new();
+            for field in file_schema.fields() {
+                match table_schema.field_with_name(field.name()) {
+                    Ok(table_field) if table_field.data_type() != field.data_type() => {
+                        changed_columns.push(ColumnTypeChange {
+                            name: field.name().clone(),
+                            file_type: field.data_type().to_string(),
+                            table_type: table_field.data_type().to_string(),
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(_) => extra_columns.push(field.name().clone()),
+                }
+            }
+
+            if !missing_columns.is_empty()
+                || !extra_columns.is_empty()
+                || !changed_columns.is_empty()
+            {
+                diffs.push(FileSchemaDiff {
+                    path: file.location.to_string(),
+                    missing_columns,
+                    extra_columns,
+                    changed_columns,
+                });
+            }
+        }
+    }
+
+    Ok(diffs)
+}
+
+/// Read the statistics of a file, from the statistics cache of the session when possible
+async fn file_statistics(
+    state: &SessionState,
+    store: &Arc<dyn ObjectStore>,
+    options: &ListingOptions,
+    file_schema: &SchemaRef,
+    file: &ObjectMeta,
+) -> Result<Arc<Statistics>> {
+    let cache = state.runtime_env().cache_manager.get_file_statistic_cache();
+    if let Some(statistics) = cache
+        .as_ref()
+        .and_then(|cache| cache.get_with_extra(&file.location, file))
+    {
+        return Ok(statistics);
+    }
+
+    let statistics = options
+        .format
+        .infer_stats(state, store, Arc::clone(file_schema), file)
+        .await?;
+    let statistics = Arc::new(statistics);
+    if let Some(cache) = cache {
+        cache.put_with_extra(&file.location, Arc::clone(&statistics), file);
+    }
+
+    Ok(statistics)
+}
+
+/// Value of each of the partition `columns` of a file, read from its `key=value` directories
+fn partition_values(
+    table_path: &ListingTableUrl,
+    file: &ObjectMeta,
+    columns: &[String],
+) -> Vec<String> {
+    let parts = file
+        .location
+        .prefix_match(table_path.prefix())
+        .map(|parts| parts.collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    columns
+        .iter()
+        .map(|column| {
+            parts
+                .iter()
+                .filter_map(|part| part.as_ref().split_once('='))
+                .find(|(key, _)| key == column)
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Aggregate the statistics of the files of a listing table, per table and per partition.
+/// Only formats that store statistics in their metadata (Parquet) report row counts and column
+/// statistics, the files of other formats are only counted
+pub(crate) async fn table_statistics(
+    state: &SessionState,
+    table: &ListingTable,
+) -> Result<TableStatistics> {
+    let options = table.options();
+    let partition_columns = options
+        .table_partition_cols
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+
+    // Partition columns are not part of the files
+    let table_schema = table.schema();
+    let fields = table_schema
+        .fields()
+        .iter()
+        .filter(|f| !partition_columns.contains(f.name()))
+        .cloned()
+        .collect::<Vec<_>>();
+    let file_schema = Arc::new(Schema::new(fields));
+
+    let mut file_count = 0;
+    let mut total_size = 0;
+    let mut row_count = Some(0);
+    let mut null_counts = vec![Some(0); file_schema.fields().len()];
+    // Bounds of a column are `None` once a file does not know them
+    let mut bounds: Vec<Option<Option<(ScalarValue, ScalarValue)>>> =
+        vec![Some(None); file_schema.fields().len()];
+    let mut partitions: BTreeMap<Vec<String>, PartitionStatistics> = BTreeMap::new();
+
+    for table_path in table.table_paths() {
+        let store = state.runtime_env().object_store(table_path)?;
+        for file in list_files(state, store.as_ref(), options, table_path).await? {
+            let statistics = file_statistics(state, &store, options, &file_schema, &file).await?;
+            let rows = statistics.num_rows.get_value().copied();
+
+            file_count += 1;
+            total_size += file.size;
+            row_count = row_count.zip(rows).map(|(total, rows)| total + rows);
+
+            let columns = statistics.column_statistics.iter();
+            for ((column, null_count), bounds) in columns.zip(&mut null_counts).zip(&mut bounds) {
+                let nulls = column.null_count.get_value().copied();
+                *null_count = null_count.zip(nulls).map(|(total, nulls)| total + nulls);
+
+                let min = column.min_value.get_value().filter(|v| !v.is_null());
+                let max = column.max_value.get_value().filter(|v| !v.is_null());
+                *bounds = match (bounds.take(), min, max) {
+                    (Some(None), Some(min), Some(max)) => Some(Some((min.clone(), max.clone()))),
+                    (Some(Some((lower, upper))), Some(min), Some(max)) => Some(Some((
+                        if min < &lower { min.clone() } else { lower },
+                        if max > &upper { max.clone() } else { upper },
+                    ))),
+                    _ => None,
+                };
+            }
+
+            if !partition_columns.is_empty() {
+                let values = partition_values(table_path, &file, &partition_columns);
+                let partition =
+                    partitions
+                        .entry(values.clone())
+                        .or_insert_with(|| PartitionStatistics {
+                            values,
+                            file_count: 0,
+                            total_size: 0,
+                            row_count: Some(0),
+                        });
+                partition.file_count += 1;
+                partition.total_size += file.size;
+                partition.row_count = partition
+                    .row_count
+                    .zip(rows)
+                    .map(|(total, rows)| total + rows);
+            }
+        }
+    }
+
+    let columns = file_schema
+        .fields()
+        .iter()
+        .zip(null_counts)
+        .zip(bounds)
+        .map(|((field, null_count), bounds)| {
+            let (min, max) = bounds
+                .flatten()
+                .map(|(min, max)| (Some(min.to_string()), Some(max.to_string())))
+                .unwrap_or_default();
+            ColumnStatistics {
+                name: field.name().clone(),
+                min,
+                max,
+                null_count,
+            }
+        })
+        .collect();
+
+    Ok(TableStatistics {
+        file_count,
+        total_size,
+        row_count,
+        columns,
+        partitions: partitions.into_values().collect(),
+    })
+}
+
 pub(super) fn factory() -> Arc<dyn TableProviderFactory> {
     Arc::new(LensTableProvider)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use datafusion::prelude::SessionContext;
+    use object_store::{memory::InMemory, path::Path, PutPayload};
+    use url::Url;
+
+    use super::*;
+
+    fn schema(fields: Vec<(&str, DataType)>) -> SchemaRef {
+        Arc::new(Schema::new(
+            fields
+                .into_iter()
+                .map(|(name, data_type)| Field::new(name, data_type, false))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    fn object(location: &str) -> ObjectMeta {
+        ObjectMeta {
+            location: Path::from(location),
+            last_modified: Utc::now(),
+            size: 0,
+            e_tag: None,
+            version: None,
+        }
+    }
+
+    /// Session that creates tables with our factory, whose `memory://bucket` locations are
+    /// stored in the returned store
+    fn session() -> (SessionContext, Arc<dyn ObjectStore>) {
+        let ctx = SessionContext::new();
+        {
+            let state_ref = ctx.state_ref();
+            let mut state = state_ref.write();
+            for factory in state.table_factories_mut().values_mut() {
+                *factory = super::factory();
+            }
+        }
+
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        ctx.register_object_store(&Url::parse("memory://bucket").unwrap(), Arc::clone(&store));
+        (ctx, store)
+    }
+
+    async fn put(store: &Arc<dyn ObjectStore>, location: &str, data: &'static str) {
+        store
+            .put(
+                &Path::from(location),
+                PutPayload::from_static(data.as_bytes()),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn widen_keeps_every_value() {
+        use DataType::*;
+
+        assert_eq!(widen(&Int32, &Int32), Some(Int32));
+        assert_eq!(widen(&Null, &Utf8), Some(Utf8));
+        assert_eq!(widen(&Int8, &Int64), Some(Int64));
+        assert_eq!(widen(&UInt32, &UInt16), Some(UInt32));
+        assert_eq!(widen(&Int8, &UInt8), Some(Int16));
+        assert_eq!(widen(&Int64, &UInt16), Some(Int64));
+        assert_eq!(widen(&UInt32, &Int32), Some(Int64));
+        assert_eq!(widen(&Float32, &Float64), Some(Float64));
+        assert_eq!(widen(&Int32, &Float32), Some(Float64));
+        assert_eq!(widen(&Utf8, &LargeUtf8), Some(LargeUtf8));
+        assert_eq!(widen(&Binary, &BinaryView), Some(LargeBinary));
+    }
+
+    #[test]
+    fn widen_refuses_lossy_conversions() {
+        use DataType::*;
+
+        assert_eq!(widen(&UInt64, &Int64), None);
+        assert_eq!(widen(&Int64, &Float64), None);
+        assert_eq!(widen(&Utf8, &Int32), None);
+        assert_eq!(widen(&Binary, &Utf8), None);
+    }
+
+    #[test]
+    fn union_by_name_merges_columns_by_name() {
+        let merged = union_by_name(vec![
+            (
+                "a.csv".to_string(),
+                schema(vec![("id", DataType::Int32), ("name", DataType::Utf8)]),
+            ),
+            (
+                "b.csv".to_string(),
+                schema(vec![("id", DataType::Int64), ("price", DataType::Float64)]),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            merged,
+            Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, true),
+                Field::new("price", DataType::Float64, true),
+            ])
+        );
+    }
+
+    #[test]
+    fn union_by_name_fails_on_unsafe_conflicts() {
+        let err = union_by_name(vec![
+            ("a.csv".to_string(), schema(vec![("id", DataType::Int64)])),
+            ("b.csv".to_string(), schema(vec![("id", DataType::Utf8)])),
+        ])
+        .unwrap_err();
+
+        assert!(err.to_string().contains("Column id of b.csv"));
+    }
+
+    #[test]
+    fn partition_types_are_inferred_from_values() {
+        let values = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+
+        assert_eq!(infer_partition_type(&values(&["1", "-2"])), DataType::Int64);
+        assert_eq!(
+            infer_partition_type(&values(&["2024-01-01", "2023-12-31"])),
+            DataType::Date32
+        );
+        assert_eq!(
+            infer_partition_type(&values(&["1", "eu"])),
+            default_partition_type()
+        );
+        assert_eq!(
+            infer_partition_type(&values(&["1", HIVE_DEFAULT_PARTITION])),
+            default_partition_type()
+        );
+    }
+
+    #[test]
+    fn discovered_partitions_are_shared_by_every_file() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let table_paths = vec![ListingTableUrl::parse("memory://bucket/t/").unwrap()];
+        let listings = vec![(
+            store,
+            vec![
+                object("t/year=2023/region=eu/a.csv"),
+                object("t/year=2024/region=us/b.csv"),
+                object("t/year=2024/c.csv"),
+            ],
+        )];
+
+        assert_eq!(
+            discover_partitions(&table_paths, &listings),
+            vec![("year".to_string(), DataType::Int64)]
+        );
+    }
+
+    #[test]
+    fn discovery_stops_at_plain_directories() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let table_paths = vec![ListingTableUrl::parse("memory://bucket/t/").unwrap()];
+        let listings = vec![(store, vec![object("t/2024/region=eu/a.csv")])];
+
+        assert!(discover_partitions(&table_paths, &listings).is_empty());
+    }
+
+    #[test]
+    fn filtered_stores_only_exist_with_patterns() {
+        let cmd = |options: &[(&str, &str)]| CreateExternalTable {
+            name: "t".into(),
+            location: "memory://bucket/t/".to_string(),
+            file_type: "CSV".to_string(),
+            table_partition_cols: vec![],
+            if_not_exists: false,
+            definition: None,
+            order_exprs: vec![],
+            unbounded: false,
+            options: options
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            constraints: datafusion::common::Constraints::empty(),
+            column_defaults: Default::default(),
+            schema: Arc::new(datafusion::common::DFSchema::empty()),
+        };
+
+        assert!(filtered_stores(&cmd(&[])).unwrap().is_empty());
+
+        let stores = filtered_stores(&cmd(&[
+            ("lens.include", "*.csv"),
+            ("lens.locations", r#"["memory://bucket/u/"]"#),
+        ]))
+        .unwrap();
+        assert_eq!(stores.len(), 2);
+        assert_ne!(stores[0], stores[1]);
+        assert_eq!(
+            stores,
+            filtered_stores(&cmd(&[
+                ("lens.include", "*.csv"),
+                ("lens.locations", r#"["memory://bucket/u/"]"#),
+            ]))
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn create_discovers_partitions_and_merges_by_name() {
+        let (ctx, store) = session();
+        put(&store, "t/year=2023/a.json", r#"{"id": 1, "name": "a"}"#).await;
+        put(&store, "t/year=2024/b.json", r#"{"id": 2, "price": 1.5}"#).await;
+
+        ctx.sql(
+            "CREATE EXTERNAL TABLE t STORED AS JSON LOCATION 'memory://bucket/t/' \
+             OPTIONS ('lens.discover_partitions' 'true', 'lens.schema_merge' 'union_by_name')",
+        )
+        .await
+        .unwrap();
+
+        let table = ctx.table_provider("t").await.unwrap();
+        let schema = table.schema();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                ("id", DataType::Int64),
+                ("name", DataType::Utf8),
+                ("price", DataType::Float64),
+                ("year", DataType::Int64),
+            ]
+        );
+        assert_eq!(partition_columns(table.as_ref()), vec!["year"]);
+
+        let rows = ctx
+            .sql("SELECT * FROM t")
+            .await
+            .unwrap()
+            .count()
+            .await
+            .unwrap();
+        assert_eq!(rows, 2);
+    }
+
+    #[tokio::test]
+    async fn csv_schemas_can_not_be_merged_by_name() {
+        let (ctx, store) = session();
+        put(&store, "t/a.csv", "id\n1\n").await;
+
+        let err = ctx
+            .sql(
+                "CREATE EXTERNAL TABLE t STORED AS CSV LOCATION 'memory://bucket/t/' \
+                 OPTIONS ('format.has_header' 'true', 'lens.schema_merge' 'union_by_name')",
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("read by position"));
+    }
+
+    #[tokio::test]
+    async fn create_reads_only_included_files() {
+        let (ctx, store) = session();
+        put(&store, "t/a.csv", "id\n1\n").await;
+        put(&store, "t/b.csv", "id\n2\n").await;
+        put(&store, "t/skipped/c.csv", "id\n3\n").await;
+
+        ctx.sql(
+            "CREATE EXTERNAL TABLE t STORED AS CSV LOCATION 'memory://bucket/t/' \
+             OPTIONS ('format.has_header' 'true', 'lens.include' '*.csv', \
+             'lens.exclude' 'b.csv')",
+        )
+        .await
+        .unwrap();
+
+        let rows = ctx
+            .sql("SELECT * FROM t")
+            .await
+            .unwrap()
+            .count()
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+}
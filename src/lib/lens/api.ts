@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api";
-import type { AwsSSOProfile, Database, DatasourceConfig, DataType, ExportOptions, Row, StreamId, StreamInfo, TimeUnit, TimeZone } from "./types";
+import type { AwsSSOProfile, CopyOptions, CreateTableOptions, Database, DatasourceConfig, DataType, ExportOptions, FilePreview, FileSchemaDiff, ObjectInfo, ObjectPage, ParquetMetadata, Row, Schema, StreamId, StreamInfo, TableFormat, TableStatistics, TimeUnit, TimeZone } from "./types";
 
 export type AwsCredentials = {
   accessKeyId: string,
@@ -14,11 +14,27 @@ export type Client = {
   }
   create: {
     datasource: (config: DatasourceConfig) => Promise<void>,
+    table: (options: CreateTableOptions) => Promise<void>,
+    schema: (name: string, catalog?: string, ifNotExists?: boolean) => Promise<void>,
+    catalog: (name: string, ifNotExists?: boolean) => Promise<void>,
   },
 
+  drop: {
+    table: (table: string, ifExists?: boolean) => Promise<void>,
+    schema: (name: string, catalog?: string, cascade?: boolean) => Promise<void>,
+    catalog: (name: string, cascade?: boolean) => Promise<void>,
+  },
+
+  file: {
+    parquetMetadata: (path: string) => Promise<ParquetMetadata>,
+    headObject: (datasource: string, path: string) => Promise<ObjectInfo>,
+    preview: (path: string, format?: TableFormat, limit?: number) => Promise<FilePreview>,
+  }
+
   list: {
     datasources: () => Promise<DatasourceConfig[]>,
     databases: () => Promise<Database[]>,
+    objects: (datasource: string, prefix?: string, delimiter?: boolean, pageToken?: string, pageSize?: number) => Promise<ObjectPage>,
   }
 
   sql: {
@@ -29,9 +45,141 @@ export type Client = {
 
   stream: {
     export: (streamId: StreamId, options: ExportOptions) => Promise<number>,
+    copy: (streamId: StreamId, options: CopyOptions) => Promise<string>,
     close: (streamId: StreamId) => Promise<void>,
     list: () => Promise<StreamInfo[]>,
   }
+
+  table: {
+    schemaDiff: (table: string) => Promise<FileSchemaDiff[]>,
+    statistics: (table: string) => Promise<TableStatistics>,
+    rename: (table: string, newName: string) => Promise<void>,
+    refresh: (table: string) => Promise<void>,
+  }
+}
+
+const toTimeUnit = (tu: any): TimeUnit | undefined => {
+  if (typeof tu === "string") {
+    switch (tu.toLowerCase()) {
+      case "second":
+        return "Second";
+      case "millisecond":
+        return "Millisecond";
+      case "microsecond":
+        return "Microsecond";
+      case "nanosecond":
+        return "Nanosecond";
+    }
+  }
+
+  return undefined;
+}
+
+const toTimezone = (tz: any): TimeZone => {
+  if (typeof tz === "string") {
+    return tz;
+  }
+
+  return undefined;
+}
+
+const toDataType = (dt: any): DataType | undefined => {
+  if (typeof dt === "string") {
+    const dataType = dt.toLowerCase();
+
+    switch (dataType) {
+      case "null":
+        return {
+          kind: dataType,
+          logical: "null",
+        };
+      case "boolean":
+        return {
+          kind: dataType,
+          logical: "boolean",
+        };
+      case "int8":
+      case "int16":
+      case "int32":
+      case "int64":
+      case "uint8":
+      case "uint16":
+      case "uint32":
+      case "uint64":
+        return {
+          kind: dataType,
+          logical: "integer",
+        };
+      case "float32":
+      case "float64":
+        return {
+          kind: dataType,
+          logical: "decimal",
+        };
+      case "date32":
+      case "date64":
+        return {
+          kind: dataType,
+          logical: "date",
+        };
+      case "utf8":
+      case "largeutf8":
+        return {
+          kind: dataType,
+          logical: "string",
+        };
+    }
+  } else if ("Timestamp" in dt) {
+    const [unit, tz] = dt["Timestamp"];
+    const timeUnit = toTimeUnit(unit);
+
+    if (timeUnit === undefined) {
+      return undefined;
+    }
+
+    return {
+      kind: "timestamp",
+      logical: "timestamp",
+      unit: timeUnit,
+      tz: toTimezone(tz),
+    };
+  } else if ("Dictionary" in dt) {
+    const [keyType, valueType] = dt["Dictionary"]
+
+    const [keyDataType, valueDataType] = [toDataType(keyType), toDataType(valueType)];
+    if (typeof (keyDataType) !== 'undefined' && typeof (valueDataType) !== 'undefined') {
+      return {
+        kind: "dictionary",
+        logical: "dictionary",
+        keyType: keyDataType,
+        valueType: valueDataType,
+      };
+    }
+  }
+
+  return undefined;
+}
+
+const toSchema = (schemaModel: any): Schema => {
+  return {
+    fields: schemaModel.fields.map(
+      (fieldModel: any) => {
+        let data_type = toDataType(
+          fieldModel.data_type,
+        );
+
+        const field = {
+          name: fieldModel.name,
+          data_type: data_type!,
+          nullable: fieldModel.nullable,
+          metadata: fieldModel.metadata,
+        };
+
+        return field;
+      },
+    ),
+    metadata: schemaModel.metadata,
+  };
 }
 
 export const client: Client = {
@@ -60,6 +208,44 @@ export const client: Client = {
     datasource: (config: DatasourceConfig): Promise<void> => {
       return invoke('create_datasource', { config })
     },
+    table: (options: CreateTableOptions): Promise<void> => {
+      return invoke('create_table', { options })
+    },
+    schema: (name: string, catalog?: string, ifNotExists: boolean = false): Promise<void> => {
+      return invoke('create_schema', { catalog, name, ifNotExists })
+    },
+    catalog: (name: string, ifNotExists: boolean = false): Promise<void> => {
+      return invoke('create_catalog', { name, ifNotExists })
+    },
+  },
+
+  drop: {
+    table: (table: string, ifExists: boolean = false): Promise<void> => {
+      return invoke('drop_table', { table, ifExists })
+    },
+    schema: (name: string, catalog?: string, cascade: boolean = false): Promise<void> => {
+      return invoke('drop_schema', { catalog, name, cascade })
+    },
+    catalog: (name: string, cascade: boolean = false): Promise<void> => {
+      return invoke('drop_catalog', { name, cascade })
+    },
+  },
+
+  file: {
+    parquetMetadata: (path: string): Promise<ParquetMetadata> => {
+      return invoke<ParquetMetadata>('parquet_metadata', { path })
+    },
+    headObject: (datasource: string, path: string): Promise<ObjectInfo> => {
+      return invoke<ObjectInfo>('head_object', { datasource, path })
+    },
+    preview: async (path: string, format?: TableFormat, limit?: number): Promise<FilePreview> => {
+      const preview = await invoke<any>('preview_file', { path, format, limit });
+      return {
+        format: preview.format,
+        schema: toSchema(preview.schema),
+        rows: preview.rows,
+      };
+    },
   },
 
   list: {
@@ -67,106 +253,6 @@ export const client: Client = {
       return invoke('list_datasources');
     },
     databases: async (): Promise<Database[]> => {
-      const toTimeUnit = (tu: any): TimeUnit | undefined => {
-        if (typeof tu === "string") {
-          switch (tu.toLowerCase()) {
-            case "second":
-              return "Second";
-            case "millisecond":
-              return "Millisecond";
-            case "microsecond":
-              return "Microsecond";
-            case "nanosecond":
-              return "Nanosecond";
-          }
-        }
-
-        return undefined;
-      }
-
-      const toTimezone = (tz: any): TimeZone => {
-        if (typeof tz === "string") {
-          return tz;
-        }
-
-        return undefined;
-      }
-      const toDataType = (dt: any): DataType | undefined => {
-        if (typeof dt === "string") {
-          const dataType = dt.toLowerCase();
-
-          switch (dataType) {
-            case "null":
-              return {
-                kind: dataType,
-                logical: "null",
-              };
-            case "boolean":
-              return {
-                kind: dataType,
-                logical: "boolean",
-              };
-            case "int8":
-            case "int16":
-            case "int32":
-            case "int64":
-            case "uint8":
-            case "uint16":
-            case "uint32":
-            case "uint64":
-              return {
-                kind: dataType,
-                logical: "integer",
-              };
-            case "float32":
-            case "float64":
-              return {
-                kind: dataType,
-                logical: "decimal",
-              };
-            case "date32":
-            case "date64":
-              return {
-                kind: dataType,
-                logical: "date",
-              };
-            case "utf8":
-            case "largeutf8":
-              return {
-                kind: dataType,
-                logical: "string",
-              };
-          }
-        } else if ("Timestamp" in dt) {
-          const [unit, tz] = dt["Timestamp"];
-          const timeUnit = toTimeUnit(unit);
-
-          if (timeUnit === undefined) {
-            return undefined;
-          }
-
-          return {
-            kind: "timestamp",
-            logical: "timestamp",
-            unit: timeUnit,
-            tz: toTimezone(tz),
-          };
-        } else if ("Dictionary" in dt) {
-          const [keyType, valueType] = dt["Dictionary"]
-
-          const [keyDataType, valueDataType] = [toDataType(keyType), toDataType(valueType)];
-          if (typeof (keyDataType) !== 'undefined' && typeof (valueDataType) !== 'undefined') {
-            return {
-              kind: "dictionary",
-              logical: "dictionary",
-              keyType: keyDataType,
-              valueType: valueDataType,
-            };
-          }
-        }
-
-        return undefined;
-      }
       const toDatabase = (db: any): Database => {
         const database: Database = {
           name: db.name,
@@ -174,29 +260,10 @@ export const client: Client = {
             const schema = {
               name: schemaModel.name,
               tables: schemaModel.tables.map((tableModel: any) => {
-                const tableSchema = {
-                  fields: tableModel.schema.fields.map(
-                    (fieldModel: any) => {
-                      let data_type = toDataType(
-                        fieldModel.data_type,
-                      );
-
-                      const field = {
-                        name: fieldModel.name,
-                        data_type: data_type!,
-                        nullable: fieldModel.nullable,
-                        metadata: fieldModel.metadata,
-                      };
-
-                      return field;
-                    },
-                  ),
-                  metadata: tableModel.schema.metadta,
-                };
-
                 const table = {
                   name: tableModel.name,
-                  schema: tableSchema,
+                  schema: toSchema(tableModel.schema),
+                  partitions: tableModel.partitions,
                 };
 
                 return table;
@@ -212,6 +279,9 @@ export const client: Client = {
 
       const dbs = await invoke<Array<any>>('list_databases');
       return dbs.map((db: any) => toDatabase(db));
+    },
+    objects: (datasource: string, prefix?: string, delimiter: boolean = true, pageToken?: string, pageSize?: number): Promise<ObjectPage> => {
+      return invoke<ObjectPage>('list_objects', { datasource, prefix, delimiter, pageToken, pageSize })
     }
   },
 
@@ -233,12 +303,30 @@ export const client: Client = {
     export: (streamId: StreamId, options: ExportOptions): Promise<number> => {
       return invoke('stream_export', { id: streamId, options })
     },
+    copy: (streamId: StreamId, options: CopyOptions): Promise<string> => {
+      return invoke<string>('stream_copy', { id: streamId, options })
+    },
     close: (streamId: StreamId): Promise<void> => {
       return invoke('stream_close', { id: streamId })
     },
     list: (): Promise<StreamInfo[]> => {
       return invoke<StreamInfo[]>('stream_list')
     }
+  },
+
+  table: {
+    schemaDiff: (table: string): Promise<FileSchemaDiff[]> => {
+      return invoke<FileSchemaDiff[]>('table_schema_diff', { table })
+    },
+    statistics: (table: string): Promise<TableStatistics> => {
+      return invoke<TableStatistics>('table_statistics', { table })
+    },
+    rename: (table: string, newName: string): Promise<void> => {
+      return invoke('rename_table', { table, newName })
+    },
+    refresh: (table: string): Promise<void> => {
+      return invoke('refresh_table', { table })
+    }
   }
 }
 
@@ -24,7 +24,118 @@ export type StoreConfig = {
 
 export type StoreType = StoreConfig['kind'];
 
-export type DatasourceConfig = { url: string, store: StoreConfig }
+export type ColumnTypeChange = {
+  name: string,
+  fileType: string,
+  tableType: string,
+};
+
+export type FileSchemaDiff = {
+  path: string,
+  missingColumns: string[],
+  extraColumns: string[],
+  changedColumns: ColumnTypeChange[],
+};
+
+export type ColumnStatistics = {
+  name: string,
+  min?: string,
+  max?: string,
+  nullCount?: number,
+};
+
+export type PartitionStatistics = {
+  values: string[],
+  fileCount: number,
+  totalSize: number,
+  rowCount?: number,
+};
+
+export type TableStatistics = {
+  fileCount: number,
+  totalSize: number,
+  rowCount?: number,
+  columns: ColumnStatistics[],
+  partitions: PartitionStatistics[],
+};
+
+export type ParquetChunkStatistics = {
+  min?: string,
+  max?: string,
+  nullCount: number,
+  distinctCount?: number,
+};
+
+export type ParquetColumnChunk = {
+  path: string,
+  physicalType: string,
+  compression: string,
+  encodings: string[],
+  numValues: number,
+  compressedSize: number,
+  uncompressedSize: number,
+  dataPageOffset: number,
+  dictionaryPageOffset?: number,
+  statistics?: ParquetChunkStatistics,
+  hasColumnIndex: boolean,
+  hasOffsetIndex: boolean,
+  hasBloomFilter: boolean,
+};
+
+export type ParquetRowGroup = {
+  ordinal?: number,
+  numRows: number,
+  totalByteSize: number,
+  compressedSize: number,
+  columns: ParquetColumnChunk[],
+};
+
+export type ParquetColumn = {
+  path: string,
+  physicalType: string,
+  logicalType?: string,
+  convertedType: string,
+  maxDefinitionLevel: number,
+  maxRepetitionLevel: number,
+};
+
+export type ParquetMetadata = {
+  path: string,
+  size: number,
+  version: number,
+  numRows: number,
+  createdBy?: string,
+  schema: string,
+  columns: ParquetColumn[],
+  rowGroups: ParquetRowGroup[],
+  keyValueMetadata: Record<string, string | null>,
+};
+
+export type ObjectInfo = {
+  path: string,
+  size: number,
+  lastModified: string,
+  eTag?: string,
+};
+
+export type ObjectPage = {
+  baseUrl: string,
+  prefixes: string[],
+  objects: ObjectInfo[],
+  nextPageToken?: string,
+};
+
+export type DatasourceConfig = { url: string, store: StoreConfig, name?: string }
+
+export type Schema = {
+  fields: {
+    name: string,
+    data_type: DataType,
+    nullable: boolean,
+    metadata: Record<string, string>
+  }[],
+  metadata: Record<string, string>,
+}
 
 export type Database = {
   name: string,
@@ -32,15 +143,8 @@ export type Database = {
     name: string
     tables: {
       name: string,
-      schema: {
-        fields: {
-          name: string,
-          data_type: DataType,
-          nullable: boolean,
-          metadata: Record<string, string>
-        }[],
-        metadata: Record<string, string>,
-      }
+      schema: Schema,
+      partitions: string[],
     }[]
   }[]
 }
@@ -221,14 +325,197 @@ export type AwsSSOProfile = {
 
 export type ExportFileFormat = Exclude<FileType, 'avro' | 'arrow'>;
 
+export type LineTerminator = 'lf' | 'crlf';
+
+export type CsvExportOptions = {
+  delimiter?: string;
+  quote?: string;
+  escape?: string;
+  hasHeader?: boolean;
+  nullValue?: string;
+  dateFormat?: string;
+  timestampFormat?: string;
+  timestampTzFormat?: string;
+  timeFormat?: string;
+  terminator?: LineTerminator;
+};
+
+export type ParquetCompression = 'uncompressed' | 'snappy' | 'gzip' | 'lzo' | 'brotli' | 'lz4' | 'lz4Raw' | 'zstd';
+
+export type ParquetExportOptions = {
+  compression?: ParquetCompression;
+  compressionLevel?: number;
+  maxRowGroupSize?: number;
+  dictionaryEnabled?: boolean;
+  statisticsEnabled?: 'none' | 'chunk' | 'page';
+  bloomFilters?: Record<string, { fpp?: number, ndv?: number }>;
+  writerVersion?: '1.0' | '2.0';
+  keyValueMetadata?: Record<string, string>;
+};
+
+export type JsonExportOptions = {
+  explicitNulls?: boolean;
+  dateFormat?: string;
+  timestampFormat?: string;
+  timestampTzFormat?: string;
+  timeFormat?: string;
+};
+
+export type ArrowIpcExportOptions = {
+  layout?: 'file' | 'stream';
+  compression?: 'lz4' | 'zstd';
+};
+
+export type XlsxExportOptions = {
+  sheetName?: string;
+  dateFormat?: string;
+  datetimeFormat?: string;
+};
+
+export type AvroExportOptions = {
+  codec?: 'null' | 'deflate' | 'snappy' | 'zstandard' | 'bzip2' | 'xz';
+  recordName?: string;
+};
+
+export type SqlExportOptions = {
+  dialect?: 'postgres' | 'mysql' | 'sqlite';
+  tableName?: string;
+  rowsPerStatement?: number;
+  createTable?: boolean;
+};
+
+export type DatabaseExportOptions = {
+  tableName?: string;
+};
+
+export type ExportFormat = {
+  kind: 'csv',
+  options: CsvExportOptions
+} |
+{
+  kind: 'parquet',
+  options: ParquetExportOptions
+} |
+{
+  kind: 'json',
+  options: JsonExportOptions
+} |
+{
+  kind: 'ndjson',
+  options: JsonExportOptions
+} |
+{
+  kind: 'arrowIpc',
+  options: ArrowIpcExportOptions
+} |
+{
+  kind: 'xlsx',
+  options: XlsxExportOptions
+} |
+{
+  kind: 'avro',
+  options: AvroExportOptions
+} |
+{
+  kind: 'sql',
+  options: SqlExportOptions
+} |
+{
+  kind: 'sqlite',
+  options: DatabaseExportOptions
+} |
+{
+  kind: 'duckdb',
+  options: DatabaseExportOptions
+};
+
+export type FileCompression = 'uncompressed' | 'gzip' | 'zstd' | 'bzip2' | 'xz';
+
+export type WriteMode = 'errorIfExists' | 'append' | 'overwrite';
+
+export type PartitionExpr = {
+  name: string;
+  expr: string;
+};
+
+export type FileNameTemplate = {
+  prefix?: string;
+  timestamp?: boolean;
+  sequenceDigits?: number;
+};
+
 export type ExportOptions = {
-  format: ExportFileFormat;
+  format: ExportFormat;
   writeOptions: {
-    overwrite: boolean;
+    mode?: WriteMode;
     singleFile: boolean;
     partitionBy: string[];
+    partitionExprs?: PartitionExpr[];
+    maxRowsPerFile?: number;
+    maxBufferedBatches?: number;
+    minimumParallelFiles?: number;
+    fileName?: FileNameTemplate;
   };
   path: string;
+  datasource?: string;
+  compression?: FileCompression;
+};
+
+export type CsvTableOptions = {
+  hasHeader?: boolean;
+  delimiter?: string;
+  quote?: string;
+  escape?: string;
+  compression?: FileCompression;
+};
+
+export type JsonTableOptions = {
+  compression?: FileCompression;
+};
+
+export type TableFormat = {
+  kind: 'csv',
+  options: CsvTableOptions
+} |
+{
+  kind: 'json',
+  options: JsonTableOptions
+} |
+{
+  kind: 'parquet'
+} |
+{
+  kind: 'avro'
+} |
+{
+  kind: 'arrow'
+};
+
+export type CreateTableOptions = {
+  catalog?: string;
+  schema?: string;
+  name: string;
+  locations: string[];
+  format: TableFormat;
+  partitionBy?: string[];
+  include?: string[];
+  exclude?: string[];
+  ifNotExists?: boolean;
+};
+
+export type FilePreview = {
+  format: TableFormat;
+  schema: Schema;
+  rows: Row[];
+};
+
+export type TableMarkup = 'markdown' | 'html';
+
+export type CopyOptions = {
+  markup: TableMarkup;
+  offset?: number;
+  limit?: number;
+  maxCellWidth?: number;
 };
 
 export type StreamInfo = {